@@ -1,14 +1,18 @@
 mod models;
 
-use nalgebra::{Point3, Point4, UnitQuaternion, Vector3};
+use nalgebra::{Isometry3, Point3, Point4, UnitQuaternion, Vector3};
 use rencan_render::core::{
+    animation::{Animation, Channel, Interpolation, LightRef, Track},
     light::{DirectionLight, LightInfo, PointLight},
-    model::AppModel,
+    model::{AppModel, CullMode},
     Model, Scene,
 };
-use std::time::{Duration, Instant};
+use std::{
+    f32::consts::{FRAC_PI_2, PI},
+    time::{Duration, Instant},
+};
 use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
@@ -34,7 +38,7 @@ fn make_pyramid(position: Point3<f32>, scale: f32) -> AppModel {
         ],
     );
     model.position = position;
-    model.scaling = scale;
+    model.scaling = Vector3::repeat(scale);
 
     AppModel::new(model)
 }
@@ -48,10 +52,11 @@ fn make_plane(position: Point3<f32>, scale: f32) -> AppModel {
             [0.4, 0.0, 0.4, 0.0].into(),   // C
             [-0.4, 0.0, 0.4, 0.0].into(),  // D
         ],
-        vec![[0, 2, 1, 0].into(), [0, 3, 2, 0].into(), [0, 1, 3, 0].into(), [1, 2, 3, 0].into()],
-    );
+        vec![[0, 2, 1, 0].into(), [0, 3, 2, 0].into()],
+    )
+    .with_cull_mode(CullMode::None);
     plane.position = position;
-    plane.scaling = scale;
+    plane.scaling = Vector3::repeat(scale);
 
     AppModel::new(plane)
 }
@@ -64,10 +69,12 @@ fn main() {
 
     let mut frames = 0;
     let mut next = Instant::now() + Duration::from_secs(1);
+    let mut cursor = (0, 0);
 
-    let mut models = models::make_desk(Point3::new(0.0, -1.5, 0.0), 3.0);
-    models.push(models::make_room([0.0, 2.5, 0.0].into(), 5.0));
-    models.push(models::make_mirror(Point3::new(0.0, 0.0, -4.9), 2.5));
+    let models = vec![
+        models::make_room([0.0, 2.5, 0.0].into(), 5.0),
+        models::make_mirror(Point3::new(0.0, 0.0, -4.9), 2.5),
+    ];
     /*
         for i in 0..20 {
             let model = make_pyramid(Point3::new((i * 5) as f32, 0.0, 0.0), 3.0);
@@ -76,33 +83,45 @@ fn main() {
             models.push(plane);
         }
     */
-    let (rot_tx, rot_rx) = std::sync::mpsc::sync_channel(1000);
+    let sun_direction = Vector3::new(0.2, -0.4, 0.3);
+    // one revolution around the x axis in 2 * PI seconds
+    let sun_track = (0..=4).fold(Track::new(), |track, i| {
+        let angle = i as f32 * FRAC_PI_2;
+        let rotation = UnitQuaternion::from_euler_angles(-angle, 0.0, 0.0);
+        track.with_key(angle, rotation * sun_direction, Interpolation::Linear)
+    });
 
     let mut scene = Scene::new(
         app.device(),
         models,
-        DirectionLight::new(
+        vec![DirectionLight::new(
             LightInfo::new(Point4::new(1.0, 0.98, 0.96, 0.0), 2.0),
-            Vector3::new(0.2, -0.4, 0.3),
-        ),
+            sun_direction,
+        )
+        .with_angular_diameter(0.05)],
         vec![
             PointLight::new(
                 LightInfo::new(Point4::new(0.8, 0.2, 0.0, 0.0), 30.0),
                 Point3::new(0.0, 2.49, 0.0),
-            ),
+            )
+            .with_radius(0.2),
             PointLight::new(
                 LightInfo::new(Point4::new(0.1, 0.9, 0.1, 0.0), 10.0),
                 Point3::new(0.0, -2.0, 0.0),
             ),
         ],
+    )
+    .with_ambient_light(LightInfo::new(Point4::new(1.0, 1.0, 1.0, 0.0), 0.3))
+    .with_animation(
+        Animation::new().with_channel(Channel::LightDirection(LightRef::Direction(0), sun_track)),
     );
 
-    std::thread::spawn(move || loop {
-        std::thread::sleep(Duration::from_millis(10));
-        if let Err(_) = rot_tx.send(UnitQuaternion::<f32>::from_euler_angles(-0.01, 0.0, 0.0)) {
-            break;
-        }
-    });
+    let desk = scene.add_node(None, Isometry3::identity());
+    for model in models::make_desk(Point3::new(0.0, -1.5, 0.0), 3.0) {
+        scene.insert_into(desk, model);
+    }
+
+    let start = Instant::now();
 
     let microseconds_per_frame = (1000_000.0 / 60.0) as u64;
     let frame_duration = Duration::from_micros(microseconds_per_frame);
@@ -117,14 +136,26 @@ fn main() {
             Event::WindowEvent { event: WindowEvent::Resized(_), .. } => {
                 app.reacreate_swapchain();
             }
+            Event::WindowEvent { event: WindowEvent::CursorMoved { position, .. }, .. } => {
+                cursor = (position.x as u32, position.y as u32);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed, button: MouseButton::Left, ..
+                    },
+                ..
+            } => {
+                println!("Picked: {:?}", app.pick(cursor.0, cursor.1));
+            }
             Event::RedrawEventsCleared => {
-                //rx.recv().unwrap();
-                while let Ok(rot) = rot_rx.try_recv() {
-                    scene.global_light.direction = rot * &scene.global_light.direction;
-                }
+                scene.evaluate(start.elapsed().as_secs_f32() % (2.0 * PI));
                 frames += 1;
                 if Instant::now() >= next {
                     println!("fps: {}", frames);
+                    if let Some(stats) = app.frame_stats() {
+                        println!("{}", stats);
+                    }
                     next = Instant::now() + Duration::from_secs(1);
                     frames = 0;
                 }
@@ -139,8 +170,13 @@ fn main() {
                 ..
             } => {
                 println!("{:?}", input.virtual_keycode.as_ref());
+                let key = input.virtual_keycode.unwrap();
+                if app.handle_key(key) {
+                    return;
+                }
                 let app = app.app_mut();
-                match input.virtual_keycode.unwrap() {
+                let desk = scene.node_mut(desk).unwrap();
+                match key {
                     VirtualKeyCode::Left => {
                         app.update_camera(|cam| cam.rotate(0.0, 0.05, 0.0));
                     }
@@ -165,6 +201,26 @@ fn main() {
                     VirtualKeyCode::S => {
                         app.update_camera(|cam| cam.move_at(0.0, 0.0, 0.05));
                     }
+                    VirtualKeyCode::Q => {
+                        desk.transform.append_rotation_mut(&UnitQuaternion::from_euler_angles(
+                            0.0, 0.05, 0.0,
+                        ));
+                    }
+                    VirtualKeyCode::E => {
+                        desk.transform.append_rotation_mut(&UnitQuaternion::from_euler_angles(
+                            0.0, -0.05, 0.0,
+                        ));
+                    }
+                    VirtualKeyCode::H => {
+                        desk.visible = !desk.visible;
+                    }
+                    VirtualKeyCode::T => {
+                        app.set_gpu_timings(app.frame_stats().is_none());
+                    }
+                    VirtualKeyCode::B => {
+                        let samples = if app.shutter_samples() == 1 { 8 } else { 1 };
+                        app.set_shutter_samples(samples);
+                    }
                     _ => {}
                 }
             }
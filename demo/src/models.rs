@@ -1,5 +1,8 @@
 use nalgebra::{Point3, Vector3};
-use rencan_render::core::{model::AppModel, Model};
+use rencan_render::core::{
+    model::{AppModel, Visibility},
+    Model,
+};
 
 macro_rules! indices {
     ($($x:ident, $y:ident, $z:ident,)*) => {
@@ -62,7 +65,7 @@ fn make_top_desk(position: Point3<f32>, scale: f32) -> AppModel {
         ],
     );
     model.position = position;
-    model.scaling = scale;
+    model.scaling = Vector3::repeat(scale);
 
     AppModel::new(model)
 }
@@ -97,7 +100,7 @@ fn make_desk_leg(position: Point3<f32>, scale: f32) -> AppModel {
         ],
     );
     model.position = position;
-    model.scaling = scale;
+    model.scaling = Vector3::repeat(scale);
 
     AppModel::new(model)
 }
@@ -136,7 +139,7 @@ pub fn make_room(position: Point3<f32>, scale: f32) -> AppModel {
         ],
     );
     model.position = position;
-    model.scaling = scale;
+    model.scaling = Vector3::repeat(scale);
 
     AppModel::new(model)
 }
@@ -164,9 +167,11 @@ pub fn make_mirror(position: Point3<f32>, scale: f32) -> AppModel {
         ],
     );
     model.position = position;
-    model.scaling = scale;
+    model.scaling = Vector3::repeat(scale);
     model.albedo = 0.0;
     model.specularity = 1.0;
+    // A flat mirror never sees itself, skipping it saves reflection rays a test.
+    model.visibility = Visibility::CAMERA | Visibility::SHADOW;
 
     AppModel::new(model)
 }
@@ -0,0 +1,367 @@
+use nalgebra::{Point3, Point4, Translation3, UnitQuaternion, Vector3};
+
+use crate::{camera::Camera, light::LightInfo, model::ModelHandle, node::NodeHandle, Scene};
+
+/// How a value changes from a keyframe to the next one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    /// Holds the value until the next keyframe.
+    Step,
+    /// Linear for numbers and vectors, spherical for rotations.
+    Linear,
+    /// Linear interpolation eased by the cubic Bezier curve from `(0, 0)` to `(1, 1)` with the
+    /// control points `(x1, y1, x2, y2)`, like `cubic-bezier` in CSS.
+    Bezier([f32; 4]),
+}
+
+impl Interpolation {
+    /// Progress of the interpolation at the fraction `t` of the time between keyframes.
+    fn progress(self, t: f32) -> f32 {
+        match self {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => t,
+            Interpolation::Bezier([x1, y1, x2, y2]) => {
+                let bezier = |p1: f32, p2: f32, s: f32| {
+                    let r = 1.0 - s;
+                    3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+                };
+                // x(s) is monotonic for control points inside [0; 1], so bisection finds s.
+                let (x1, x2) = (x1.max(0.0).min(1.0), x2.max(0.0).min(1.0));
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..24 {
+                    let middle = (low + high) * 0.5;
+                    if bezier(x1, x2, middle) < t {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                bezier(y1, y2, (low + high) * 0.5)
+            }
+        }
+    }
+}
+
+/// Value which can be interpolated between keyframes.
+pub trait Animatable: Clone {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Animatable for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Animatable for Vector3<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(other, t)
+    }
+}
+
+impl Animatable for Point3<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Point3::from(self.coords.lerp(&other.coords, t))
+    }
+}
+
+impl Animatable for Point4<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Point4::from(self.coords.lerp(&other.coords, t))
+    }
+}
+
+impl Animatable for UnitQuaternion<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        // falls back to nlerp for opposite rotations, where slerp is undefined
+        self.try_slerp(other, t, 1.0e-6).unwrap_or_else(|| self.nlerp(other, t))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe<T> {
+    /// Seconds from the start of the animation.
+    pub time: f32,
+    pub value: T,
+    /// Interpolation towards the next keyframe.
+    pub interpolation: Interpolation,
+}
+
+/// Keyframes of one value sorted by time.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new() -> Self {
+        Track { keyframes: vec![] }
+    }
+
+    pub fn with_key(mut self, time: f32, value: T, interpolation: Interpolation) -> Self {
+        let index =
+            self.keyframes.iter().position(|k| k.time > time).unwrap_or(self.keyframes.len());
+        self.keyframes.insert(index, Keyframe { time, value, interpolation });
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Value at the time, clamped to the first and the last keyframes. `None` for empty tracks.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        match next {
+            None => self.keyframes.last().map(|k| k.value.clone()),
+            Some(0) => Some(self.keyframes[0].value.clone()),
+            Some(next) => {
+                let (from, to) = (&self.keyframes[next - 1], &self.keyframes[next]);
+                let t = (time - from.time) / (to.time - from.time);
+                Some(from.value.interpolate(&to.value, from.interpolation.progress(t)))
+            }
+        }
+    }
+}
+
+impl<T: Animatable> Default for Track<T> {
+    fn default() -> Self {
+        Track::new()
+    }
+}
+
+/// Light of a [`Scene`] by its index in the list of lights of its kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightRef {
+    Direction(usize),
+    Point(usize),
+    Spot(usize),
+}
+
+/// Track bound to the property which it animates.
+#[derive(Debug, Clone)]
+pub enum Channel {
+    ModelPosition(ModelHandle, Track<Point3<f32>>),
+    ModelRotation(ModelHandle, Track<UnitQuaternion<f32>>),
+    ModelScaling(ModelHandle, Track<Vector3<f32>>),
+    NodeTranslation(NodeHandle, Track<Vector3<f32>>),
+    NodeRotation(NodeHandle, Track<UnitQuaternion<f32>>),
+    LightColor(LightRef, Track<Point4<f32>>),
+    LightIntensity(LightRef, Track<f32>),
+    /// Ignored for direction lights.
+    LightPosition(LightRef, Track<Point3<f32>>),
+    /// Ignored for point lights. Sampled directions are normalized.
+    LightDirection(LightRef, Track<Vector3<f32>>),
+}
+
+/// Tracks of the camera. Not animated properties keep their values.
+#[derive(Debug, Clone, Default)]
+pub struct CameraAnimation {
+    pub position: Option<Track<Point3<f32>>>,
+    /// Interpolated spherically and converted to the Euler angles of [`Camera::rotate`].
+    pub rotation: Option<Track<UnitQuaternion<f32>>>,
+    pub fov: Option<Track<f32>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    pub channels: Vec<Channel>,
+    pub camera: CameraAnimation,
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Animation::default()
+    }
+
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn with_camera(mut self, camera: CameraAnimation) -> Self {
+        self.camera = camera;
+        self
+    }
+
+    /// Applies all channels at the time to the scene. Models, nodes and lights which no longer
+    /// exist are skipped.
+    pub fn apply(&self, scene: &mut Scene, time: f32) {
+        for channel in &self.channels {
+            apply_channel(channel, scene, time);
+        }
+    }
+
+    /// The camera at the time, to be passed to [`App::update_camera`](crate::app::App).
+    pub fn evaluate_camera(&self, time: f32, camera: Camera) -> Camera {
+        let position = self.camera.position.as_ref().and_then(|t| t.sample(time));
+        let rotation = self.camera.rotation.as_ref().and_then(|t| t.sample(time));
+        let fov = self.camera.fov.as_ref().and_then(|t| t.sample(time));
+
+        let animated = Camera::new(
+            position.unwrap_or(*camera.position()),
+            rotation.map_or(*camera.rotation(), |r| r.euler_angles()),
+            fov.unwrap_or(camera.fov()),
+        );
+        match camera.shutter_end() {
+            Some(&(position, rotation)) => animated.with_shutter_end(position, rotation),
+            None => animated,
+        }
+    }
+}
+
+fn apply_channel(channel: &Channel, scene: &mut Scene, time: f32) {
+    match channel {
+        Channel::ModelPosition(handle, track) => {
+            if let (Some(model), Some(value)) = (scene.get_mut(*handle), track.sample(time)) {
+                model.update_info(|m| m.position = value);
+            }
+        }
+        Channel::ModelRotation(handle, track) => {
+            if let (Some(model), Some(value)) = (scene.get_mut(*handle), track.sample(time)) {
+                model.update_info(|m| m.rotation = value);
+            }
+        }
+        Channel::ModelScaling(handle, track) => {
+            if let (Some(model), Some(value)) = (scene.get_mut(*handle), track.sample(time)) {
+                model.update_info(|m| m.scaling = value);
+            }
+        }
+        Channel::NodeTranslation(handle, track) => {
+            if let (Some(node), Some(value)) = (scene.node_mut(*handle), track.sample(time)) {
+                node.transform.translation = Translation3::from(value);
+            }
+        }
+        Channel::NodeRotation(handle, track) => {
+            if let (Some(node), Some(value)) = (scene.node_mut(*handle), track.sample(time)) {
+                node.transform.rotation = value;
+            }
+        }
+        Channel::LightColor(light, track) => {
+            if let (Some(info), Some(value)) = (light_info(scene, *light), track.sample(time)) {
+                info.color = value;
+            }
+        }
+        Channel::LightIntensity(light, track) => {
+            if let (Some(info), Some(value)) = (light_info(scene, *light), track.sample(time)) {
+                info.intensity = value;
+            }
+        }
+        Channel::LightPosition(light, track) => {
+            let position = match *light {
+                LightRef::Direction(_) => None,
+                LightRef::Point(i) => scene.point_lights.get_mut(i).map(|l| &mut l.position),
+                LightRef::Spot(i) => scene.spot_lights.get_mut(i).map(|l| &mut l.position),
+            };
+            if let (Some(position), Some(value)) = (position, track.sample(time)) {
+                *position = value;
+            }
+        }
+        Channel::LightDirection(light, track) => {
+            let direction = match *light {
+                LightRef::Direction(i) => {
+                    scene.direction_lights.get_mut(i).map(|l| &mut l.direction)
+                }
+                LightRef::Point(_) => None,
+                LightRef::Spot(i) => scene.spot_lights.get_mut(i).map(|l| &mut l.direction),
+            };
+            // interpolated directions are shorter than the keyframes, opposite ones cancel out
+            let value = track.sample(time).and_then(|v| v.try_normalize(0.0));
+            if let (Some(direction), Some(value)) = (direction, value) {
+                *direction = value;
+            }
+        }
+    }
+}
+
+fn light_info(scene: &mut Scene, light: LightRef) -> Option<&mut LightInfo> {
+    match light {
+        LightRef::Direction(i) => scene.direction_lights.get_mut(i).map(|l| &mut l.info),
+        LightRef::Point(i) => scene.point_lights.get_mut(i).map(|l| &mut l.info),
+        LightRef::Spot(i) => scene.spot_lights.get_mut(i).map(|l| &mut l.info),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        light::PointLight,
+        model::{AppModel, Model},
+        primitive::Primitive,
+        scene::tests::device,
+    };
+
+    fn track() -> Track<f32> {
+        Track::new()
+            .with_key(2.0, 4.0, Interpolation::Linear)
+            .with_key(1.0, 2.0, Interpolation::Step)
+            .with_key(3.0, 8.0, Interpolation::Linear)
+    }
+
+    #[test]
+    fn keys_stay_sorted() {
+        let times = track().keyframes().iter().map(|k| k.time).collect::<Vec<_>>();
+        assert_eq!(times, vec![1.0, 2.0, 3.0]);
+        assert_eq!(track().duration(), 3.0);
+    }
+
+    #[test]
+    fn sample_clamps_to_keyframes() {
+        assert_eq!(track().sample(0.0), Some(2.0));
+        assert_eq!(track().sample(10.0), Some(8.0));
+        assert_eq!(Track::<f32>::new().sample(0.0), None);
+    }
+
+    #[test]
+    fn sample_interpolates() {
+        // step holds the value of the first keyframe
+        assert_eq!(track().sample(1.5), Some(2.0));
+        assert_eq!(track().sample(2.0), Some(4.0));
+        assert_eq!(track().sample(2.5), Some(6.0));
+    }
+
+    #[test]
+    fn progress() {
+        for &t in [0.0, 0.25, 0.5, 0.9, 1.0].iter() {
+            assert_eq!(Interpolation::Step.progress(t), 0.0);
+            assert_eq!(Interpolation::Linear.progress(t), t);
+            let linear_bezier = Interpolation::Bezier([0.0, 0.0, 1.0, 1.0]).progress(t);
+            assert!((linear_bezier - t).abs() < 1e-4);
+        }
+        let ease_in = Interpolation::Bezier([0.42, 0.0, 1.0, 1.0]);
+        assert!(ease_in.progress(0.5) < 0.5);
+        assert!(ease_in.progress(0.0).abs() < 1e-4);
+        assert!((ease_in.progress(1.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn apply_skips_removed_handles() {
+        let device = match device() {
+            Some(device) => device,
+            None => return,
+        };
+        let sphere = || AppModel::new(Model::from_primitive(Primitive::Sphere { radius: 1.0 }));
+        let light =
+            PointLight::new(LightInfo::new(Point4::new(1.0, 1.0, 1.0, 1.0), 1.0), Point3::origin());
+        let mut scene = Scene::new(device, vec![], vec![], vec![light]);
+        let removed = scene.insert(sphere());
+        scene.remove(removed);
+        let kept = scene.insert(sphere());
+
+        let position = Track::new().with_key(0.0, Point3::new(1.0, 2.0, 3.0), Interpolation::Step);
+        let intensity = Track::new().with_key(0.0, 5.0, Interpolation::Step);
+        Animation::new()
+            .with_channel(Channel::ModelPosition(removed, position))
+            .with_channel(Channel::LightIntensity(LightRef::Point(1), intensity))
+            .apply(&mut scene, 0.0);
+
+        assert_eq!(scene.get(kept).unwrap().model().position, Point3::origin());
+        assert_eq!(scene.point_lights[0].info.intensity, 1.0);
+    }
+}
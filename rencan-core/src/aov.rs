@@ -0,0 +1,42 @@
+/// Arbitrary output variables which can be rendered alongside the beauty image.
+///
+/// Every AOV is written to its own `R32G32B32A32Sfloat` image. Alpha is `1.0` for pixels with a
+/// hit and `0.0` for pixels without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aov {
+    /// Distance from the camera plane along the view direction, in `r`.
+    Depth,
+    /// World normal of the hit, in `rgb`.
+    Normal,
+    /// Albedo of the hit model, in `rgb`.
+    Albedo,
+    /// Handle of the hit model, the index in `r` and the generation in `g`.
+    ObjectId,
+    /// Barycentric coordinates of the hit on the triangle, in `rg`.
+    Barycentrics,
+    /// Cosine between the normal and the direction to the camera, in `rgb`.
+    FacingRatio,
+    /// World position of the hit, in `rgb`.
+    Position,
+    /// Texture coordinates of the hit interpolated from the vertices, in `rg`. Zero for
+    /// primitives and models without texture coordinates.
+    Uv,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::Barycentrics,
+        Aov::FacingRatio,
+        Aov::Position,
+        Aov::Uv,
+    ];
+
+    /// Value of the AOV kind in shaders.
+    pub fn into_uniform(self) -> u32 {
+        self as u32
+    }
+}
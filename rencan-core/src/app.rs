@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{cell::RefCell, sync::Arc};
 
 use crevice::std140::AsStd140;
 use vulkano::{
@@ -8,16 +8,20 @@ use vulkano::{
 };
 
 use crate::{
-    camera::{Camera, CameraUniform},
-    intersection::IntersectionUniform,
-    light::{DirectionLight, DirectionLightUniform},
+    camera::{Camera, CameraUniform, Shutter},
+    debug_view::DebugView,
+    frame_stats::{FrameStats, GpuTimer, QueryCommand},
+    intersection::{Intersection, IntersectionUniform, PickResult},
     model_buffers::SceneBuffers,
     ray::Ray,
+    ray_cast::{RayCastMode, RayCaster},
     AppInfo, BufferAccessData, CommandFactory, CommandFactoryContext, Scene, Screen,
 };
 use vulkano::{
-    buffer::CpuBufferPool,
-    command_buffer::CommandBufferExecError,
+    buffer::{BufferSlice, CpuAccessibleBuffer, CpuBufferPool},
+    command_buffer::{
+        AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer, CommandBufferExecError,
+    },
     descriptor::{DescriptorSet, PipelineLayoutAbstract},
     device::Device,
     instance::QueueFamily,
@@ -28,6 +32,10 @@ pub struct App {
     camera: Camera,
     commands: Vec<Box<dyn CommandFactory>>,
     buffers: GlobalBuffers,
+    timer: Option<RefCell<GpuTimer>>,
+    debug_view: DebugView,
+    ray_caster: RayCaster,
+    shutter_samples: u32,
 }
 
 impl App {
@@ -37,7 +45,32 @@ impl App {
         commands: Vec<Box<dyn CommandFactory>>,
         buffers: GlobalBuffers,
     ) -> Self {
-        Self { info, camera, commands, buffers }
+        let ray_caster = RayCaster::new(info.device.clone());
+        Self {
+            info,
+            camera,
+            commands,
+            buffers,
+            timer: None,
+            debug_view: DebugView::Lit,
+            ray_caster,
+            shutter_samples: 1,
+        }
+    }
+    pub fn with_gpu_timings(mut self) -> Self {
+        self.set_gpu_timings(true);
+        self
+    }
+    /// Starts or stops measuring GPU time of every command factory. Does nothing if the timings
+    /// are already in the requested state, so the averages are kept.
+    pub fn set_gpu_timings(&mut self, enabled: bool) {
+        if enabled == self.timer.is_some() {
+            return;
+        }
+        self.timer = match enabled {
+            true => Some(RefCell::new(GpuTimer::new(&self.info.device, self.commands.len()))),
+            false => None,
+        };
     }
     pub fn info(&self) -> &AppInfo {
         &self.info
@@ -45,6 +78,11 @@ impl App {
     pub fn camera(&self) -> &Camera {
         &self.camera
     }
+    /// GPU durations of every command factory. Returns `None` unless the timings are enabled
+    /// with [`AppBuilder::with_gpu_timings`] or [`App::set_gpu_timings`].
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.timer.as_ref().map(|timer| timer.borrow().stats().clone())
+    }
     pub fn update_screen(&mut self, screen: Screen) {
         self.info.screen = screen;
         self.buffers.resize_buffers(
@@ -56,6 +94,22 @@ impl App {
     pub fn update_camera(&mut self, update_cam: impl FnOnce(Camera) -> Camera) {
         self.camera = update_cam(self.camera.clone());
     }
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+    /// Switches the final shading. Takes effect from the next rendered frame.
+    pub fn set_debug_view(&mut self, view: DebugView) {
+        self.debug_view = view;
+    }
+    pub fn shutter_samples(&self) -> u32 {
+        self.shutter_samples
+    }
+    /// Renders every frame as the average of this count of sub-frames, each covering its own
+    /// part of the shutter interval. Models and the camera with shutter end transforms are
+    /// blurred along their motion. 1 disables the motion blur.
+    pub fn set_shutter_samples(&mut self, samples: u32) {
+        self.shutter_samples = samples.max(1);
+    }
     pub fn render<Prev, F>(
         &self,
         previous: Prev,
@@ -70,44 +124,157 @@ impl App {
         F: FnOnce(&AppInfo) -> Arc<dyn ImageViewAccess + Send + Sync + 'static>,
     {
         let image = image_create(&self.info);
-        let buffers = self.create_buffers(image.clone(), scene);
-        let ctx = CommandFactoryContext {
-            app_info: &self.info,
-            buffers: buffers.clone(),
-            scene,
-            camera: &self.camera,
-        };
+        let scene_buffers = scene.frame_buffers();
 
         let mut commands = vec![];
-        for factory in self.commands.iter() {
-            factory.make_command(ctx.clone(), &mut commands);
+        for sample in 0..self.shutter_samples {
+            let shutter = Shutter { sample, samples: self.shutter_samples };
+            let buffers = self.create_buffers(image.clone(), scene_buffers.clone(), shutter);
+            let ctx = CommandFactoryContext {
+                app_info: &self.info,
+                buffers: buffers.clone(),
+                scene,
+                camera: &self.camera,
+                debug_view: self.debug_view,
+                shutter,
+            };
+
+            commands.push(FrameCommand::Pass(self.reset_ambient_occlusion(&buffers)));
+            // Only the first sub-frame is timed, the others take the same time.
+            match self.timer.as_ref().filter(|_| sample == 0) {
+                Some(timer) => {
+                    let mut timer = timer.borrow_mut();
+                    let family = self.info.graphics_queue.family();
+
+                    timer.begin_frame();
+                    commands.push(FrameCommand::Query(timer.reset_command(family)));
+                    for factory in self.commands.iter() {
+                        let begin = timer.begin_pass(factory.name(), family);
+                        commands.push(FrameCommand::Query(begin));
+                        let mut pass = vec![];
+                        factory.make_command(ctx.clone(), &mut pass);
+                        commands.extend(pass.into_iter().map(FrameCommand::Pass));
+                        commands.push(FrameCommand::Query(timer.end_pass(family)));
+                    }
+                    timer.end_frame();
+                }
+                None => {
+                    let mut passes = vec![];
+                    for factory in self.commands.iter() {
+                        factory.make_command(ctx.clone(), &mut passes);
+                    }
+                    commands.extend(passes.into_iter().map(FrameCommand::Pass));
+                }
+            }
         }
 
         let mut fut: Box<dyn GpuFuture> = Box::new(previous);
 
         for command in commands {
-            let f = fut.then_execute(self.info.graphics_queue.clone(), command)?;
-            fut = Box::new(f);
+            let queue = self.info.graphics_queue.clone();
+            fut = match command {
+                FrameCommand::Pass(command) => Box::new(fut.then_execute(queue, command)?),
+                FrameCommand::Query(command) => Box::new(fut.then_execute(queue, command)?),
+            };
         }
 
         Ok((fut, image))
     }
+    fn reset_ambient_occlusion(&self, buffers: &Buffers) -> AutoCommandBuffer {
+        let mut command = AutoCommandBufferBuilder::new(
+            self.info.device.clone(),
+            self.info.graphics_queue.family(),
+        )
+        .unwrap();
+        command.fill_buffer(buffers.ambient_occlusion.clone(), 1.0f32.to_bits()).unwrap();
+        command.build().unwrap()
+    }
+    /// Returns the primary hit under the pixel of the last rendered frame.
+    ///
+    /// Blocks until the intersections are downloaded. The previous frame must be finished.
+    pub fn pick(&self, x: u32, y: u32) -> Option<PickResult> {
+        self.pick_rect(x, y, 1, 1).pop()
+    }
+    /// Returns all primary hits inside the rectangle of the last rendered frame, row by row.
+    /// Pixels without a hit are skipped.
+    ///
+    /// Blocks until the intersections are downloaded. The previous frame must be finished.
+    pub fn pick_rect(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<PickResult> {
+        let screen_width = self.info.screen.width();
+        let x_end = x.saturating_add(width).min(screen_width);
+        let y_end = y.saturating_add(height).min(self.info.screen.height());
+        if x >= x_end || y >= y_end {
+            return vec![];
+        }
+
+        let first = (y * screen_width) as usize;
+        let last = (y_end * screen_width) as usize;
+
+        let device = self.info.device.clone();
+        let source = BufferSlice::from_typed_buffer_access(self.buffers.intersections.clone())
+            .slice(first..last)
+            .unwrap();
+        let destination = unsafe {
+            CpuAccessibleBuffer::<[IntersectionUniform]>::uninitialized_array(
+                device.clone(),
+                last - first,
+                BufferUsage { transfer_destination: true, ..BufferUsage::none() },
+                false,
+            )
+            .unwrap()
+        };
+
+        let mut command =
+            AutoCommandBufferBuilder::new(device, self.info.graphics_queue.family()).unwrap();
+        command.copy_buffer(source, destination.clone()).unwrap();
+        command
+            .build()
+            .unwrap()
+            .execute(self.info.graphics_queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let intersections = destination.read().unwrap();
+        (y..y_end)
+            .flat_map(|py| (x..x_end).map(move |px| (px, py)))
+            .filter_map(|(px, py)| {
+                let idx = (py * screen_width + px) as usize - first;
+                intersections[idx].into_pick_result(px, py)
+            })
+            .collect()
+    }
+    /// Traces the rays against the scene and returns their intersections in the same order.
+    ///
+    /// Blocks until the GPU finishes.
+    pub fn cast_rays(&self, scene: &Scene, rays: &[Ray], mode: RayCastMode) -> Vec<Intersection> {
+        self.ray_caster.cast(&self.info, scene, rays, mode)
+    }
     fn create_buffers(
         &self,
         image: Arc<dyn ImageViewAccess + Send + Sync + 'static>,
-        scene: &Scene,
+        scene_buffers: SceneBuffers,
+        shutter: Shutter,
     ) -> Buffers {
         self.buffers.make_buffers(
             self.info.device.clone(),
             &self.info,
             &self.camera,
+            shutter,
             image,
-            &scene.global_light,
-            &scene,
+            scene_buffers,
         )
     }
 }
 
+/// Command buffers of a frame in the order of execution.
+enum FrameCommand {
+    Pass(AutoCommandBuffer),
+    Query(QueryCommand),
+}
+
 pub struct GlobalAppBuffers {
     pub rays: Arc<DeviceLocalBuffer<[Ray]>>,
     pub intersections: Arc<DeviceLocalBuffer<[IntersectionUniform]>>,
@@ -116,9 +283,10 @@ pub struct GlobalAppBuffers {
 pub struct GlobalBuffers {
     rays: Arc<DeviceLocalBuffer<[Ray]>>,
     intersections: Arc<DeviceLocalBuffer<[IntersectionUniform]>>,
+    ambient_occlusion: Arc<DeviceLocalBuffer<[f32]>>,
+    accumulation: Arc<DeviceLocalBuffer<[[f32; 4]]>>,
     camera: Arc<CpuBufferPool<<CameraUniform as AsStd140>::Std140Type>>,
     screen: Arc<CpuBufferPool<Screen>>,
-    direction_light: Arc<CpuBufferPool<DirectionLightUniform>>,
 }
 
 impl GlobalBuffers {
@@ -139,17 +307,16 @@ impl GlobalBuffers {
                 size,
                 BufferUsage {
                     storage_buffer: true,
+                    transfer_source: true,
                     ..BufferUsage::none()
                 },
                 std::iter::once(family.clone()),
             )
             .unwrap(),
+            ambient_occlusion: make_ambient_occlusion_buffer(device, family, size),
+            accumulation: make_accumulation_buffer(device, family, size),
             camera: Arc::new(CpuBufferPool::new(device.clone(), BufferUsage::uniform_buffer())),
             screen: Arc::new(CpuBufferPool::new(device.clone(), BufferUsage::uniform_buffer())),
-            direction_light: Arc::new(CpuBufferPool::new(
-                device.clone(),
-                BufferUsage::uniform_buffer(),
-            )),
         }
     }
 
@@ -162,19 +329,20 @@ impl GlobalBuffers {
         device: Arc<Device>,
         app: &AppInfo,
         camera: &Camera,
+        shutter: Shutter,
         image: Arc<dyn ImageViewAccess + Send + Sync + 'static>,
-        light: &DirectionLight,
-        scene: &Scene,
+        scene_buffers: SceneBuffers,
     ) -> Buffers {
         Buffers::new(
             device,
             self.rays.clone(),
             self.intersections.clone(),
-            Arc::new(self.camera.next(camera.clone().into_uniform().as_std140()).unwrap()),
+            self.ambient_occlusion.clone(),
+            self.accumulation.clone(),
+            Arc::new(self.camera.next(camera.clone().into_uniform(shutter).as_std140()).unwrap()),
             Arc::new(self.screen.next(app.screen.clone()).unwrap()),
             image,
-            Arc::new(self.direction_light.next(light.clone().into_uniform()).unwrap()),
-            scene.frame_buffers(),
+            scene_buffers,
         )
     }
 
@@ -194,18 +362,55 @@ impl GlobalBuffers {
             new_size,
             BufferUsage {
                     storage_buffer: true,
+                    transfer_source: true,
                     ..BufferUsage::none()
                 },
             std::iter::once(family.clone()),
         )
         .unwrap();
+        self.ambient_occlusion = make_ambient_occlusion_buffer(device, family, new_size);
+        self.accumulation = make_accumulation_buffer(device, family, new_size);
     }
 }
 
+fn make_accumulation_buffer(
+    device: &Arc<Device>,
+    family: QueueFamily,
+    size: usize,
+) -> Arc<DeviceLocalBuffer<[[f32; 4]]>> {
+    DeviceLocalBuffer::array(
+        device.clone(),
+        size,
+        BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+        std::iter::once(family),
+    )
+    .unwrap()
+}
+
+fn make_ambient_occlusion_buffer(
+    device: &Arc<Device>,
+    family: QueueFamily,
+    size: usize,
+) -> Arc<DeviceLocalBuffer<[f32]>> {
+    DeviceLocalBuffer::array(
+        device.clone(),
+        size,
+        BufferUsage { storage_buffer: true, transfer_destination: true, ..BufferUsage::none() },
+        std::iter::once(family),
+    )
+    .unwrap()
+}
+
 #[derive(Clone)]
 pub struct Buffers {
     pub rays: Arc<dyn BufferAccessData<Data = [Ray]> + Send + Sync>,
     pub intersections: Arc<DeviceLocalBuffer<[IntersectionUniform]>>,
+    /// Visibility of the ambient light for every pixel, 1.0 unless an ambient occlusion pass
+    /// writes it.
+    pub ambient_occlusion: Arc<DeviceLocalBuffer<[f32]>>,
+    /// Sum of the sub-frames of a motion blurred frame for every pixel.
+    pub accumulation: Arc<DeviceLocalBuffer<[[f32; 4]]>>,
+    pub output_image: Arc<dyn ImageViewAccess + Send + Sync>,
     pub global_app_set: Arc<dyn DescriptorSet + Send + Sync>,
     pub models_set: Arc<dyn DescriptorSet + Send + Sync>,
     pub lights_set: Arc<dyn DescriptorSet + Send + Sync>,
@@ -217,14 +422,13 @@ impl Buffers {
         device: Arc<Device>,
         rays: Arc<DeviceLocalBuffer<[Ray]>>,
         intersections: Arc<DeviceLocalBuffer<[IntersectionUniform]>>,
+        ambient_occlusion: Arc<DeviceLocalBuffer<[f32]>>,
+        accumulation: Arc<DeviceLocalBuffer<[[f32; 4]]>>,
         camera: Arc<
             dyn BufferAccessData<Data = <CameraUniform as AsStd140>::Std140Type> + Send + Sync,
         >,
         screen: Arc<dyn BufferAccessData<Data = Screen> + Send + Sync>,
         output_image: Arc<dyn ImageViewAccess + Send + Sync>,
-        direction_light: Arc<
-            dyn BufferAccessData<Data = DirectionLightUniform> + Send + Sync + 'static,
-        >,
         models_buffers: SceneBuffers,
     ) -> Self {
         mod cs {
@@ -236,9 +440,7 @@ impl Buffers {
 
         let shader = cs::Shader::load(device.clone()).unwrap();
         let pipeline = Arc::new(
-            vulkano::pipeline::ComputePipeline::new(device, &shader.main_entry_point(), &cs::SpecializationConstants {
-                constant_0: 1,
-            }, None)
+            vulkano::pipeline::ComputePipeline::new(device, &shader.main_entry_point(), &(), None)
                 .unwrap(),
         );
 
@@ -256,43 +458,59 @@ impl Buffers {
             .unwrap()
             .add_image(output_image.clone())
             .unwrap()
-            .build()
-            .unwrap(),
-        );
-
-        let models_set = Arc::new(
-            PersistentDescriptorSet::start(
-                pipeline.layout().descriptor_set_layout(1).unwrap().clone(),
-            )
-            .add_buffer(models_buffers.count.clone())
-            .unwrap()
-            .add_buffer(models_buffers.infos.clone())
-            .unwrap()
-            .add_buffer(models_buffers.vertices.clone())
-            .unwrap()
-            .add_buffer(models_buffers.indices.clone())
-            .unwrap()
-            .add_buffer(models_buffers.hit_boxes.clone())
+            .add_buffer(ambient_occlusion.clone())
             .unwrap()
             .build()
             .unwrap(),
         );
 
+        let models_set =
+            models_buffers.models_set(pipeline.layout().descriptor_set_layout(1).unwrap().clone());
+
         let lights_set = Arc::new(
             PersistentDescriptorSet::start(
                 pipeline.layout().descriptor_set_layout(2).unwrap().clone(),
             )
-            .add_buffer(direction_light)
+            .add_buffer(models_buffers.direction_lights_count.clone())
+            .unwrap()
+            .add_buffer(models_buffers.direction_lights.clone())
             .unwrap()
             .add_buffer(models_buffers.point_lights_count.clone())
             .unwrap()
             .add_buffer(models_buffers.point_lights.clone())
             .unwrap()
+            .add_buffer(models_buffers.background.clone())
+            .unwrap()
+            .add_buffer(models_buffers.background_pixels.clone())
+            .unwrap()
+            .add_buffer(models_buffers.emitters_count.clone())
+            .unwrap()
+            .add_buffer(models_buffers.emitters.clone())
+            .unwrap()
+            .add_buffer(models_buffers.spot_lights_count.clone())
+            .unwrap()
+            .add_buffer(models_buffers.spot_lights.clone())
+            .unwrap()
+            .add_buffer(models_buffers.ies_profiles.clone())
+            .unwrap()
+            .add_buffer(models_buffers.ambient_light.clone())
+            .unwrap()
+            .add_buffer(models_buffers.medium.clone())
+            .unwrap()
             .build()
             .unwrap(),
         );
 
-        Buffers { rays, intersections, global_app_set, models_set, lights_set }
+        Buffers {
+            rays,
+            intersections,
+            ambient_occlusion,
+            accumulation,
+            output_image,
+            global_app_set,
+            models_set,
+            lights_set,
+        }
     }
 }
 
@@ -303,6 +521,7 @@ pub struct AppBuilder {
     camera: Camera,
     commands: Vec<Box<dyn CommandFactory>>,
     global_buffers: GlobalBuffers,
+    gpu_timings: bool,
 }
 
 impl AppBuilder {
@@ -324,13 +543,23 @@ impl AppBuilder {
             info.graphics_queue.family(),
             info.size_of_image_array(),
         );
-        Self { info, camera, commands: vec![], global_buffers: buffers }
+        Self { info, camera, commands: vec![], global_buffers: buffers, gpu_timings: false }
     }
     pub fn then_command(mut self, f: Box<dyn CommandFactory>) -> Self {
         self.commands.push(f);
         self
     }
+    /// Measures GPU time of every command factory with timestamp queries. Results are available
+    /// through [`App::frame_stats`].
+    pub fn with_gpu_timings(mut self) -> Self {
+        self.gpu_timings = true;
+        self
+    }
     pub fn build(self) -> App {
-        App::new(self.info, self.camera, self.commands, self.global_buffers)
+        let app = App::new(self.info, self.camera, self.commands, self.global_buffers);
+        match self.gpu_timings {
+            true => app.with_gpu_timings(),
+            false => app,
+        }
     }
 }
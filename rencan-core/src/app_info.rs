@@ -1,4 +1,7 @@
-use crate::Screen;
+use crate::{
+    tile::{self, Tile, DEFAULT_TILE_SIZE, LOCAL_SIZE},
+    Screen,
+};
 use std::sync::Arc;
 use vulkano::{
     device::{Device, Queue},
@@ -10,6 +13,7 @@ pub struct AppInfo {
     pub graphics_queue: Arc<Queue>,
     pub device: Arc<Device>,
     pub screen: Screen,
+    pub tile_size: u32,
 }
 
 impl AppInfo {
@@ -19,10 +23,29 @@ impl AppInfo {
         device: Arc<Device>,
         screen: Screen,
     ) -> Self {
-        AppInfo { instance, graphics_queue, device, screen }
+        let tile_size = DEFAULT_TILE_SIZE.min(max_tile_size(&device));
+        AppInfo { instance, graphics_queue, device, screen, tile_size }
+    }
+
+    /// The size is clamped to at least 1 and to the largest tile which one dispatch can cover.
+    pub fn with_tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size.max(1).min(max_tile_size(&self.device));
+        self
     }
 
     pub fn size_of_image_array(&self) -> usize {
         (self.screen.width() * self.screen.height()) as usize
     }
+
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> {
+        tile::tiles(&self.screen, self.tile_size)
+    }
+}
+
+/// Side of the largest square tile within the work group count limits of the device.
+fn max_tile_size(device: &Device) -> u32 {
+    let max_workgroups = device.physical_device().limits().max_compute_work_group_count();
+    max_workgroups[0]
+        .saturating_mul(LOCAL_SIZE[0])
+        .min(max_workgroups[1].saturating_mul(LOCAL_SIZE[1]))
 }
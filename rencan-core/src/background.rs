@@ -0,0 +1,125 @@
+use std::{fmt, sync::Arc};
+
+use crevice::std140::AsStd140;
+use nalgebra::Vector3;
+
+/// Reason why pixels do not make an [`EquirectangularImage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// The width or the height is zero.
+    Empty,
+    /// `width * height` overflows.
+    TooLarge,
+    /// The count of pixels is not `width * height`.
+    PixelCount { expected: usize, actual: usize },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Empty => f.write_str("image has no pixels"),
+            ImageError::TooLarge => f.write_str("image is too large"),
+            ImageError::PixelCount { expected, actual } => {
+                write!(f, "image must have {} pixels, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Linear HDR image in equirectangular projection. Pixels are stored row by row from the top,
+/// the center of the image looks at `-z`.
+#[derive(Debug, Clone)]
+pub struct EquirectangularImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl EquirectangularImage {
+    pub fn new(width: u32, height: u32, pixels: Vec<[f32; 4]>) -> Result<Self, ImageError> {
+        if width == 0 || height == 0 {
+            return Err(ImageError::Empty);
+        }
+        let expected = (width as usize).checked_mul(height as usize).ok_or(ImageError::TooLarge)?;
+        if pixels.len() != expected {
+            return Err(ImageError::PixelCount { expected, actual: pixels.len() });
+        }
+        Ok(EquirectangularImage { width, height, pixels })
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn pixels(&self) -> &[[f32; 4]] {
+        &self.pixels
+    }
+}
+
+/// What rays that miss all geometry see.
+#[derive(Debug, Clone)]
+pub enum Background {
+    Color(Vector3<f32>),
+    /// Vertical gradient from `bottom` at the nadir to `top` at the zenith.
+    Gradient { top: Vector3<f32>, bottom: Vector3<f32> },
+    Equirectangular { image: Arc<EquirectangularImage>, intensity: f32 },
+    /// Analytic sky with the sun in the direction of the first direction light of the scene.
+    Sky { zenith: Vector3<f32>, horizon: Vector3<f32> },
+}
+
+impl Background {
+    pub fn sky() -> Self {
+        Background::Sky {
+            zenith: Vector3::new(0.15, 0.35, 0.8),
+            horizon: Vector3::new(0.7, 0.8, 0.95),
+        }
+    }
+
+    pub fn image(&self) -> Option<&Arc<EquirectangularImage>> {
+        match self {
+            Background::Equirectangular { image, .. } => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_uniform(self, image_based_lighting: bool) -> BackgroundUniform {
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        let (kind, color_a, color_b, intensity, size) = match self {
+            Background::Color(color) => (0, color, zero, 1.0, [0, 0]),
+            Background::Gradient { top, bottom } => (1, top, bottom, 1.0, [0, 0]),
+            Background::Equirectangular { image, intensity } => {
+                (2, zero, zero, intensity, [image.width, image.height])
+            }
+            Background::Sky { zenith, horizon } => (3, zenith, horizon, 1.0, [0, 0]),
+        };
+        BackgroundUniform {
+            color_a: color_a.into(),
+            kind,
+            color_b: color_b.into(),
+            intensity,
+            size: size.into(),
+            image_based_lighting: image_based_lighting as u32,
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Vector3::new(0.0, 0.7, 0.4))
+    }
+}
+
+#[derive(AsStd140)]
+pub struct BackgroundUniform {
+    color_a: mint::Vector3<f32>,
+    kind: u32,
+    color_b: mint::Vector3<f32>,
+    intensity: f32,
+    size: mint::Vector2<u32>,
+    image_based_lighting: u32,
+}
+
+pub type BackgroundUniformStd140 = <BackgroundUniform as AsStd140>::Std140Type;
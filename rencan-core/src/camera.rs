@@ -1,10 +1,14 @@
-use nalgebra::{Point3, Rotation3, Vector3};
+use nalgebra::{Matrix3, Point3, Rotation3, UnitQuaternion, Vector3};
+
+/// Position and rotation of a camera.
+pub type CameraPose = (Point3<f32>, (f32, f32, f32));
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     position: Point3<f32>,
     rotation: (f32, f32, f32),
     fov: f32,
+    shutter_end: Option<CameraPose>,
 }
 
 impl Camera {
@@ -17,11 +21,15 @@ impl Camera {
     pub fn fov(&self) -> f32 {
         self.fov
     }
+    /// Position and rotation at the closing of the shutter, `None` for a still camera.
+    pub fn shutter_end(&self) -> Option<&CameraPose> {
+        self.shutter_end.as_ref()
+    }
 }
 
 impl Camera {
     pub fn new(position: Point3<f32>, rotation: (f32, f32, f32), fov: f32) -> Self {
-        Camera { position, rotation, fov }
+        Camera { position, rotation, fov, shutter_end: None }
     }
     pub fn from_origin() -> Self {
         Camera::new(Point3::new(0.0, 0.0, 0.0), (0.0, 0.0, 0.0), 60.0f32.to_radians())
@@ -30,37 +38,92 @@ impl Camera {
         self.fov = fov;
         self
     }
+    /// Moves the camera to the position and rotation during the shutter interval, which blurs
+    /// the frame when [`App::set_shutter_samples`](crate::app::App::set_shutter_samples) is
+    /// more than 1.
+    pub fn with_shutter_end(mut self, position: Point3<f32>, rotation: (f32, f32, f32)) -> Self {
+        self.shutter_end = Some((position, rotation));
+        self
+    }
+    /// Moves the camera along its own axes. The shutter end pose moves along its axes too.
     pub fn move_at(self, x: f32, y: f32, z: f32) -> Self {
-        let vector_to_move =
-            Rotation3::from_euler_angles(self.rotation.0, self.rotation.1, self.rotation.2)
-                .transform_vector(&Vector3::new(x, y, z));
-        Camera::new(self.position + vector_to_move, self.rotation, self.fov)
+        let offset = Vector3::new(x, y, z);
+        let move_pose = |position: Point3<f32>, (roll, pitch, yaw): (f32, f32, f32)| {
+            position + Rotation3::from_euler_angles(roll, pitch, yaw).transform_vector(&offset)
+        };
+        Camera {
+            position: move_pose(self.position, self.rotation),
+            shutter_end: self
+                .shutter_end
+                .map(|(position, rotation)| (move_pose(position, rotation), rotation)),
+            ..self
+        }
     }
+    /// Rotates the camera and its shutter end pose by the same angles.
     pub fn rotate(self, roll: f32, pitch: f32, yaw: f32) -> Self {
-        let (x, y, z) = self.rotation;
-        Camera::new(self.position, (x + roll, y + pitch, z + yaw), self.fov)
+        let rotate_pose = |(x, y, z): (f32, f32, f32)| (x + roll, y + pitch, z + yaw);
+        Camera {
+            rotation: rotate_pose(self.rotation),
+            shutter_end: self
+                .shutter_end
+                .map(|(position, rotation)| (position, rotate_pose(rotation))),
+            ..self
+        }
     }
-    pub fn into_uniform(self) -> CameraUniform {
-        CameraUniform::from(self)
+    pub fn into_uniform(self, shutter: Shutter) -> CameraUniform {
+        let (end_position, end_rotation) =
+            self.shutter_end.unwrap_or((self.position, self.rotation));
+        CameraUniform {
+            position: self.position.coords.into(),
+            rotation: rotation_matrix(self.rotation).into(),
+            fov: self.fov,
+            end_position: end_position.coords.into(),
+            orientation: orientation(self.rotation),
+            end_orientation: orientation(end_rotation),
+            shutter_sample: shutter.sample,
+            shutter_samples: shutter.samples,
+        }
     }
 }
 
+/// Sub-frame of a motion blurred frame. Sub-frame `sample` traces the rays at random moments
+/// in the `sample`-th of `samples` equal parts of the shutter interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shutter {
+    pub sample: u32,
+    pub samples: u32,
+}
+
+impl Default for Shutter {
+    fn default() -> Self {
+        Shutter { sample: 0, samples: 1 }
+    }
+}
+
+fn rotation_matrix((roll, pitch, yaw): (f32, f32, f32)) -> Matrix3<f32> {
+    *Rotation3::from_euler_angles(roll, pitch, yaw).matrix()
+}
+
+/// The rotation as a quaternion with the real part last, which shaders interpolate spherically.
+fn orientation((roll, pitch, yaw): (f32, f32, f32)) -> mint::Vector4<f32> {
+    UnitQuaternion::from_euler_angles(roll, pitch, yaw).into_inner().coords.into()
+}
+
 #[derive(crevice::std140::AsStd140)]
 pub struct CameraUniform {
     position: mint::Vector3<f32>,
     rotation: mint::ColumnMatrix3<f32>,
     fov: f32,
+    end_position: mint::Vector3<f32>,
+    /// Rotations at the opening and the closing of the shutter.
+    orientation: mint::Vector4<f32>,
+    end_orientation: mint::Vector4<f32>,
+    shutter_sample: u32,
+    shutter_samples: u32,
 }
 
 impl From<Camera> for CameraUniform {
     fn from(cam: Camera) -> Self {
-        Self {
-            position: cam.position.coords.into(),
-            rotation: Rotation3::from_euler_angles(cam.rotation.0, cam.rotation.1, cam.rotation.2)
-                .matrix()
-                .clone()
-                .into(),
-            fov: cam.fov,
-        }
+        cam.into_uniform(Shutter::default())
     }
 }
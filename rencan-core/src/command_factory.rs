@@ -1,8 +1,18 @@
-use crate::{app::Buffers, camera::Camera, AppInfo, Scene};
+use crate::{
+    app::Buffers,
+    camera::{Camera, Shutter},
+    debug_view::DebugView,
+    AppInfo, Scene,
+};
 use vulkano::command_buffer::AutoCommandBuffer;
 
 pub trait CommandFactory {
     fn make_command<'m>(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>);
+
+    /// Name of the pass, used in [`FrameStats`](crate::frame_stats::FrameStats).
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 #[derive(Clone)]
@@ -11,4 +21,7 @@ pub struct CommandFactoryContext<'a> {
     pub buffers: Buffers,
     pub scene: &'a Scene,
     pub camera: &'a Camera,
+    pub debug_view: DebugView,
+    /// Sub-frame of the motion blurred frame being rendered.
+    pub shutter: Shutter,
 }
@@ -0,0 +1,46 @@
+/// What the final shading pass writes to the output image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    /// Usual lightning.
+    Lit,
+    Normals,
+    FacingRatio,
+    Checkerboard,
+    Barycentrics,
+    TriangleId,
+    ModelId,
+    /// Heat map of occluded shadow rays relative to the count of lights.
+    ShadowRayCount,
+    /// Specularity of hit models, i.e. where reflections are traced.
+    ReflectionMask,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 9] = [
+        DebugView::Lit,
+        DebugView::Normals,
+        DebugView::FacingRatio,
+        DebugView::Checkerboard,
+        DebugView::Barycentrics,
+        DebugView::TriangleId,
+        DebugView::ModelId,
+        DebugView::ShadowRayCount,
+        DebugView::ReflectionMask,
+    ];
+
+    pub fn next(self) -> Self {
+        let idx = DebugView::ALL.iter().position(|v| *v == self).unwrap();
+        DebugView::ALL[(idx + 1) % DebugView::ALL.len()]
+    }
+
+    /// Value of the view in shaders.
+    pub fn into_uniform(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        DebugView::Lit
+    }
+}
@@ -0,0 +1,119 @@
+use crevice::std140::AsStd140;
+use nalgebra::{Isometry3, Vector3};
+
+use crate::model::{triangle_area, AppModel};
+
+/// Light emitted uniformly by the front faces of all triangles of a model. Only triangle
+/// meshes are sampled as lights, models with a primitive glow with the same radiance but do not
+/// light other models. Planes are unbounded, so their emission is spread to zero radiance.
+#[derive(Debug, Clone)]
+pub struct Emission {
+    /// RGB in range [0; 1]
+    pub color: Vector3<f32>,
+    /// Total emitted power of the model.
+    pub power: f32,
+}
+
+impl Emission {
+    pub fn new(color: Vector3<f32>, power: f32) -> Self {
+        Emission { color, power }
+    }
+
+    /// Radiance of a surface with the given area emitting this power.
+    pub fn radiance(&self, area: f32) -> Vector3<f32> {
+        if !area.is_finite() || area <= 0.0 {
+            return Vector3::zeros();
+        }
+        self.color * (self.power / (std::f32::consts::PI * area))
+    }
+}
+
+/// World space triangle of an emissive model, sampled by the lightning with probability
+/// proportional to its power.
+#[derive(AsStd140)]
+pub struct EmitterUniform {
+    v0: mint::Vector3<f32>,
+    /// Probability to choose this or any previous emitter.
+    cdf: f32,
+    v1: mint::Vector3<f32>,
+    area: f32,
+    v2: mint::Vector3<f32>,
+    probability: f32,
+    radiance: mint::Vector3<f32>,
+    /// Index of the model in the models buffer.
+    model_id: u32,
+}
+
+impl EmitterUniform {
+    /// Placeholder for the buffer of a scene without emitters, which can not be empty.
+    pub fn none() -> Self {
+        let zero = mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        EmitterUniform {
+            v0: zero,
+            cdf: 0.0,
+            v1: zero,
+            area: 0.0,
+            v2: zero,
+            probability: 0.0,
+            radiance: zero,
+            model_id: 0,
+        }
+    }
+}
+
+pub type EmitterUniformStd140 = <EmitterUniform as AsStd140>::Std140Type;
+
+fn luminance(color: &Vector3<f32>) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Collects triangles of all emissive models of the scene with their sampling probabilities.
+/// Models with a primitive have no triangles and are skipped.
+/// `models` with the world transforms of their nodes must be in the order of the models buffer.
+pub fn scene_emitters<'a>(
+    models: impl Iterator<Item = (&'a AppModel, Isometry3<f32>)>,
+) -> Vec<EmitterUniform> {
+    let mut emitters = Vec::new();
+
+    for (model_id, (model, parent)) in models.enumerate() {
+        let emission = match &model.model().emission {
+            Some(emission) if emission.power > 0.0 => emission,
+            _ => continue,
+        };
+        let triangles = model.model().world_triangles(&parent).collect::<Vec<_>>();
+        let areas = triangles.iter().map(triangle_area).collect::<Vec<_>>();
+        let radiance = emission.radiance(areas.iter().sum());
+
+        for (triangle, area) in triangles.into_iter().zip(areas) {
+            if area <= 0.0 {
+                continue;
+            }
+            emitters.push(EmitterUniform {
+                v0: triangle[0].coords.into(),
+                cdf: 0.0,
+                v1: triangle[1].coords.into(),
+                area,
+                v2: triangle[2].coords.into(),
+                probability: luminance(&radiance) * area,
+                radiance: radiance.into(),
+                model_id: model_id as u32,
+            });
+        }
+    }
+
+    let total: f32 = emitters.iter().map(|e| e.probability).sum();
+    if total <= 0.0 {
+        return vec![];
+    }
+    let mut cdf = 0.0;
+    for emitter in &mut emitters {
+        emitter.probability /= total;
+        cdf += emitter.probability;
+        emitter.cdf = cdf;
+    }
+    if let Some(last) = emitters.last_mut() {
+        last.cdf = 1.0;
+    }
+
+    emitters
+}
@@ -0,0 +1,304 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    os::raw::c_void,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use vulkano::{
+    buffer::BufferAccess,
+    command_buffer::{
+        pool::standard::{StandardCommandPoolAlloc, StandardCommandPoolBuilder},
+        sys::{Flags, UnsafeCommandBuffer, UnsafeCommandBufferBuilder},
+        CommandBuffer, CommandBufferExecError, Kind,
+    },
+    device::{Device, DeviceOwned, Queue},
+    framebuffer::{FramebufferAbstract, RenderPassAbstract},
+    image::{ImageAccess, ImageLayout},
+    instance::QueueFamily,
+    query::{QueryType, UnsafeQueryPool},
+    sync::{AccessCheckError, AccessFlagBits, GpuFuture, PipelineStages},
+    VulkanObject,
+};
+
+/// How many frames are used for the rolling average.
+const AVERAGE_WINDOW: usize = 60;
+/// Query pools are reused in a ring so results of the oldest frame are most likely ready when
+/// they are read back.
+const FRAMES_IN_FLIGHT: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct PassStats {
+    pub name: &'static str,
+    pub last: Duration,
+    pub average: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    pub passes: Vec<PassStats>,
+}
+
+impl FrameStats {
+    pub fn total(&self) -> Duration {
+        self.passes.iter().map(|p| p.last).sum()
+    }
+    pub fn total_average(&self) -> Duration {
+        self.passes.iter().map(|p| p.average).sum()
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pass in self.passes.iter() {
+            writeln!(
+                f,
+                "{:>16}: {:>8.3} ms (avg {:>8.3} ms)",
+                pass.name,
+                pass.last.as_secs_f64() * 1000.0,
+                pass.average.as_secs_f64() * 1000.0
+            )?;
+        }
+        write!(
+            f,
+            "{:>16}: {:>8.3} ms (avg {:>8.3} ms)",
+            "total",
+            self.total().as_secs_f64() * 1000.0,
+            self.total_average().as_secs_f64() * 1000.0
+        )
+    }
+}
+
+struct PendingFrame {
+    pool: Arc<UnsafeQueryPool>,
+    names: Vec<&'static str>,
+    submitted: bool,
+}
+
+/// Wraps command buffers of every `CommandFactory` into timestamp queries and collects
+/// per-factory GPU durations.
+pub struct GpuTimer {
+    frames: Vec<PendingFrame>,
+    current: usize,
+    count_of_queries: u32,
+    timestamp_period: f32,
+    history: Vec<(&'static str, VecDeque<Duration>)>,
+    stats: FrameStats,
+}
+
+impl GpuTimer {
+    pub fn new(device: &Arc<Device>, count_of_passes: usize) -> Self {
+        let count_of_queries = count_of_passes.max(1) as u32 * 2;
+        let frames = (0..FRAMES_IN_FLIGHT)
+            .map(|_| PendingFrame {
+                // `queries_range` of vulkano 0.20 rejects ranges which end at the last query,
+                // so the pool has one spare query.
+                pool: Arc::new(
+                    UnsafeQueryPool::new(
+                        device.clone(),
+                        QueryType::Timestamp,
+                        count_of_queries + 1,
+                    )
+                    .unwrap(),
+                ),
+                names: vec![],
+                submitted: false,
+            })
+            .collect();
+        GpuTimer {
+            frames,
+            current: 0,
+            count_of_queries,
+            timestamp_period: device.physical_device().limits().timestamp_period(),
+            history: vec![],
+            stats: FrameStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Reads results of the frame that used the current query pool and prepares the pool for the
+    /// new frame.
+    pub fn begin_frame(&mut self) {
+        let frame = &mut self.frames[self.current];
+        if frame.submitted && !frame.names.is_empty() {
+            let mut timestamps = vec![0u64; frame.names.len() * 2];
+            if read_timestamps(&frame.pool, &mut timestamps) {
+                let names = std::mem::take(&mut frame.names);
+                self.update_stats(names, timestamps);
+            }
+        }
+        let frame = &mut self.frames[self.current];
+        frame.names.clear();
+        frame.submitted = false;
+    }
+
+    pub fn reset_command(&self, family: QueueFamily) -> QueryCommand {
+        let count = self.count_of_queries;
+        QueryCommand::record(
+            self.frames[self.current].pool.clone(),
+            family,
+            |builder, pool| unsafe {
+                builder.reset_query_pool(pool.queries_range(0, count).unwrap());
+            },
+        )
+    }
+
+    /// Makes a command buffer which writes the start timestamp of the pass with the given name.
+    pub fn begin_pass(&mut self, name: &'static str, family: QueueFamily) -> QueryCommand {
+        let frame = &mut self.frames[self.current];
+        let query = frame.names.len() as u32 * 2;
+        frame.names.push(name);
+        write_timestamp(frame.pool.clone(), query, family)
+    }
+
+    /// Makes a command buffer which writes the end timestamp of the last begun pass.
+    pub fn end_pass(&mut self, family: QueueFamily) -> QueryCommand {
+        let frame = &mut self.frames[self.current];
+        let query = frame.names.len() as u32 * 2 - 1;
+        write_timestamp(frame.pool.clone(), query, family)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.frames[self.current].submitted = true;
+        self.current = (self.current + 1) % self.frames.len();
+    }
+
+    fn update_stats(&mut self, names: Vec<&'static str>, timestamps: Vec<u64>) {
+        let period = self.timestamp_period as f64;
+        let mut passes = Vec::with_capacity(names.len());
+
+        for (i, name) in names.into_iter().enumerate() {
+            let ticks = timestamps[i * 2 + 1].saturating_sub(timestamps[i * 2]);
+            let last = Duration::from_nanos((ticks as f64 * period) as u64);
+
+            let history = match self.history.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, history)) => history,
+                None => {
+                    self.history.push((name, VecDeque::with_capacity(AVERAGE_WINDOW)));
+                    &mut self.history.last_mut().unwrap().1
+                }
+            };
+            if history.len() == AVERAGE_WINDOW {
+                history.pop_front();
+            }
+            history.push_back(last);
+            let average = history.iter().sum::<Duration>() / history.len() as u32;
+
+            passes.push(PassStats { name, last, average });
+        }
+
+        self.stats = FrameStats { passes };
+    }
+}
+
+fn write_timestamp(pool: Arc<UnsafeQueryPool>, query: u32, family: QueueFamily) -> QueryCommand {
+    QueryCommand::record(pool, family, |builder, pool| unsafe {
+        let stages = PipelineStages { bottom_of_pipe: true, ..PipelineStages::none() };
+        builder.write_timestamp(pool.query(query).unwrap(), stages);
+    })
+}
+
+/// Copies the timestamps from the start of the pool without waiting. Returns `false` if some of
+/// them are not written yet.
+fn read_timestamps(pool: &UnsafeQueryPool, timestamps: &mut [u64]) -> bool {
+    let device = pool.device();
+    let result = unsafe {
+        device.pointers().GetQueryPoolResults(
+            device.internal_object(),
+            pool.internal_object(),
+            0,
+            timestamps.len() as u32,
+            std::mem::size_of_val(timestamps),
+            timestamps.as_mut_ptr() as *mut c_void,
+            std::mem::size_of::<u64>() as u64,
+            vk_sys::QUERY_RESULT_64_BIT,
+        )
+    };
+    result == vk_sys::SUCCESS
+}
+
+/// Command buffer with query commands of [`GpuTimer`]. `AutoCommandBufferBuilder` of vulkano
+/// 0.20 can't record them, so they are recorded with the unsafe builder.
+pub struct QueryCommand {
+    inner: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+    pool: Arc<UnsafeQueryPool>,
+    submitted: AtomicBool,
+}
+
+impl QueryCommand {
+    fn record(
+        pool: Arc<UnsafeQueryPool>,
+        family: QueueFamily,
+        record: impl FnOnce(
+            &mut UnsafeCommandBufferBuilder<StandardCommandPoolBuilder>,
+            &UnsafeQueryPool,
+        ),
+    ) -> Self {
+        let command_pool = Device::standard_command_pool(pool.device(), family);
+        let inner = unsafe {
+            let mut builder = UnsafeCommandBufferBuilder::new(
+                &command_pool,
+                Kind::primary(),
+                Flags::OneTimeSubmit,
+            )
+            .unwrap();
+            record(&mut builder, &pool);
+            builder.build().unwrap()
+        };
+        QueryCommand { inner, pool, submitted: AtomicBool::new(false) }
+    }
+}
+
+unsafe impl DeviceOwned for QueryCommand {
+    fn device(&self) -> &Arc<Device> {
+        self.pool.device()
+    }
+}
+
+// Query commands access neither buffers nor images, the query pool is kept alive by the command.
+unsafe impl CommandBuffer for QueryCommand {
+    type PoolAlloc = StandardCommandPoolAlloc;
+
+    fn inner(&self) -> &UnsafeCommandBuffer<StandardCommandPoolAlloc> {
+        &self.inner
+    }
+
+    fn lock_submit(&self, _: &dyn GpuFuture, _: &Queue) -> Result<(), CommandBufferExecError> {
+        match self.submitted.swap(true, Ordering::SeqCst) {
+            true => Err(CommandBufferExecError::OneTimeSubmitAlreadySubmitted),
+            false => Ok(()),
+        }
+    }
+
+    unsafe fn unlock(&self) {}
+
+    fn check_buffer_access(
+        &self,
+        _: &dyn BufferAccess,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn check_image_access(
+        &self,
+        _: &dyn ImageAccess,
+        _: ImageLayout,
+        _: bool,
+        _: &Queue,
+    ) -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn kind(&self) -> Kind<&dyn RenderPassAbstract, &dyn FramebufferAbstract> {
+        Kind::Primary
+    }
+}
@@ -0,0 +1,231 @@
+use std::{fmt, hash, marker::PhantomData};
+
+const INDEX_BITS: u32 = 20;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+const GENERATION_MASK: u32 = (1 << (32 - INDEX_BITS)) - 1;
+
+/// Id of a value in a [`HandleMap`] which stays valid while other values are inserted and
+/// removed. A handle of a removed value never refers to a value inserted later into the same
+/// slot, until the generation of the slot wraps around.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: u32, generation: u32) -> Self {
+        Handle { index, generation, _marker: PhantomData }
+    }
+
+    /// Packs the handle into 32 bits, the lower 20 bits are the index and the upper 12 bits are
+    /// the generation. This is the id which shaders see.
+    pub fn to_raw(self) -> u32 {
+        self.index | (self.generation << INDEX_BITS)
+    }
+
+    pub fn from_raw(raw: u32) -> Self {
+        Handle::new(raw & INDEX_MASK, raw >> INDEX_BITS)
+    }
+
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> hash::Hash for Handle<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.to_raw().hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Storage which addresses values by generational handles. Slots of removed values are reused.
+pub struct HandleMap<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> HandleMap<T> {
+    pub fn new() -> Self {
+        HandleMap { slots: vec![], free: vec![], len: 0 }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        self.len += 1;
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                Handle::new(index, slot.generation)
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                assert!(index <= INDEX_MASK, "too many values in a handle map");
+                self.slots.push(Slot { generation: 0, value: Some(value) });
+                Handle::new(index, 0)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = (slot.generation + 1) & GENERATION_MASK;
+        self.free.push(handle.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Values in the order of their slots, which is the order of upload to the GPU.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(i, slot)| {
+            slot.value.as_ref().map(|value| (Handle::new(i as u32, slot.generation), value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(i, slot)| {
+            let generation = slot.generation;
+            slot.value.as_mut().map(|value| (Handle::new(i as u32, generation), value))
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+}
+
+impl<T> Default for HandleMap<T> {
+    fn default() -> Self {
+        HandleMap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_get() {
+        let mut map = HandleMap::new();
+        let a = map.insert("a");
+        let b = map.insert("b");
+        assert_ne!(a, b);
+        assert_eq!(map.get(a), Some(&"a"));
+        assert_eq!(map.get(b), Some(&"b"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(a, &"a"), (b, &"b")]);
+    }
+
+    #[test]
+    fn reuses_removed_slot_with_next_generation() {
+        let mut map = HandleMap::new();
+        let a = map.insert("a");
+        map.insert("b");
+        assert_eq!(map.remove(a), Some("a"));
+        assert_eq!(map.len(), 1);
+
+        let c = map.insert("c");
+        assert_eq!(c.index(), a.index());
+        assert_eq!(c.generation(), a.generation() + 1);
+        assert_eq!(map.get(c), Some(&"c"));
+    }
+
+    #[test]
+    fn stale_handle_returns_none() {
+        let mut map = HandleMap::new();
+        let a = map.insert("a");
+        map.remove(a);
+        assert_eq!(map.get(a), None);
+        assert_eq!(map.remove(a), None);
+        assert_eq!(map.len(), 0);
+
+        let b = map.insert("b");
+        assert_eq!(map.get(a), None);
+        assert_eq!(map.get_mut(a), None);
+        assert!(!map.contains(a));
+        assert_eq!(map.remove(a), None);
+        assert_eq!(map.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn generation_wraps_around() {
+        let mut map = HandleMap::new();
+        let first = map.insert(0);
+        let mut handle = first;
+        for i in 0..=GENERATION_MASK {
+            map.remove(handle);
+            handle = map.insert(i);
+        }
+        assert_eq!(handle.index(), first.index());
+        assert_eq!(handle.generation(), 0);
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let handle = Handle::<()>::new(INDEX_MASK, GENERATION_MASK);
+        assert_eq!(Handle::from_raw(handle.to_raw()), handle);
+        assert_eq!(Handle::<()>::from_raw(5).index(), 5);
+    }
+}
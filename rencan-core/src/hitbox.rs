@@ -56,4 +56,12 @@ pub struct HitBoxRectangleUniform {
     align2: f32,
 }
 
+impl HitBoxRectangleUniform {
+    /// Placeholder for the buffer of a scene without visible models, which can not be empty.
+    pub fn none() -> Self {
+        let zero = mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        HitBoxRectangleUniform { min_point: zero, align1: 0.0, max_point: zero, align2: 0.0 }
+    }
+}
+
 pub type HitBoxRectangleUniformStd140 = <HitBoxRectangleUniform as AsStd140>::Std140Type;
@@ -1,47 +1,49 @@
-/*#[derive(Debug, Clone)]
+use crate::model::ModelHandle;
+use nalgebra::{Point3, Vector3};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Intersection {
     Intersect {
-        model_id: u32,
+        model: ModelHandle,
         triangle_idx: u32,
         vertices_offset: u32,
         barycentric_coords: [f32; 2],
+        point: Point3<f32>,
+        /// Faces the origin of the ray.
+        normal: Vector3<f32>,
+        /// Whether the ray hit the front face, i.e. `normal` is not flipped.
+        front_face: bool,
         distance: f32,
     },
     NotIntersect,
 }
 
 impl Intersection {
-    pub fn into_uniform(self) -> IntersectionUniform {
+    pub fn is_intersect(&self) -> bool {
         match self {
-            Intersection::NotIntersect => IntersectionUniform {
-                intersect: 0,
-                model_id: 0,
-                triangle_idx: 0,
-                vertices_offset: 0,
-                barycentric_coords: mint::Vector2::from([0.0, 0.0]),
-                distance: 0.0,
-                paddings: unsafe { std::mem::MaybeUninit::uninit().assume_init() },
-            },
-            Intersection::Intersect {
-                model_id,
-                triangle_idx,
-                barycentric_coords,
-                vertices_offset,
-                distance,
-            } => IntersectionUniform {
-                intersect: 1,
-                model_id,
-                triangle_idx,
-                vertices_offset,
-                barycentric_coords: mint::Vector2::from(barycentric_coords),
-                distance,
-                paddings: unsafe { std::mem::MaybeUninit::uninit().assume_init() },
-            },
+            Intersection::Intersect { .. } => true,
+            Intersection::NotIntersect => false,
         }
     }
-}*/
+}
 
+/// Information about the hit under a pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickResult {
+    pub x: u32,
+    pub y: u32,
+    pub model: ModelHandle,
+    pub triangle_idx: u32,
+    pub point: Point3<f32>,
+    /// Faces the camera.
+    pub normal: Vector3<f32>,
+    pub front_face: bool,
+    pub distance: f32,
+}
+
+#[allow(dead_code)]
 #[repr(C)]
+#[derive(Debug, Clone, Copy)]
 pub struct IntersectionUniform {
     point: mint::Vector3<f32>,
     padding1: f32,
@@ -53,5 +55,43 @@ pub struct IntersectionUniform {
     triangle_idx: u32,
     vertices_offset: u32,
     distance: f32,
-    paddings: [f32; 1],
+    model_handle: u32,
+    front_face: u32,
+    paddings: [u32; 3],
+}
+
+impl IntersectionUniform {
+    pub fn into_pick_result(self, x: u32, y: u32) -> Option<PickResult> {
+        match self.intersect {
+            0 => None,
+            _ => Some(PickResult {
+                x,
+                y,
+                model: ModelHandle::from_raw(self.model_handle),
+                triangle_idx: self.triangle_idx,
+                point: Point3::from(Vector3::from(self.point)),
+                normal: self.normal.into(),
+                front_face: self.front_face != 0,
+                distance: self.distance,
+            }),
+        }
+    }
+}
+
+impl From<IntersectionUniform> for Intersection {
+    fn from(uniform: IntersectionUniform) -> Self {
+        match uniform.intersect {
+            0 => Intersection::NotIntersect,
+            _ => Intersection::Intersect {
+                model: ModelHandle::from_raw(uniform.model_handle),
+                triangle_idx: uniform.triangle_idx,
+                vertices_offset: uniform.vertices_offset,
+                barycentric_coords: uniform.barycentric_coords.into(),
+                point: Point3::from(Vector3::from(uniform.point)),
+                normal: uniform.normal.into(),
+                front_face: uniform.front_face != 0,
+                distance: uniform.distance,
+            },
+        }
+    }
 }
@@ -5,19 +5,32 @@ pub use model::Model;
 pub use ray::Ray;
 pub use screen::Screen;
 
+pub mod animation;
+pub mod aov;
 pub mod app;
 mod app_info;
+pub mod background;
 mod buffer;
 pub mod camera;
 mod command_factory;
+pub mod debug_view;
+pub mod emission;
+pub mod frame_stats;
+pub mod handle;
 mod hitbox;
 pub mod intersection;
 pub mod light;
+pub mod medium;
+pub mod mesh;
 pub mod model;
 mod model_buffers;
+pub mod node;
+pub mod primitive;
 pub mod queue_famile_ext;
 mod ray;
+pub mod ray_cast;
 mod scene;
 mod screen;
+pub mod tile;
 
 pub use scene::Scene;
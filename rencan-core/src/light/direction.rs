@@ -5,17 +5,32 @@ use nalgebra::Vector3;
 pub struct DirectionLight {
     pub info: LightInfo,
     pub direction: Vector3<f32>,
+    /// Angular diameter of the light source in radians, the sun is about 0.0093. Zero makes
+    /// hard shadows.
+    pub angular_diameter: f32,
+    pub cast_shadows: bool,
 }
 
 impl DirectionLight {
     pub fn new(info: LightInfo, direction: Vector3<f32>) -> Self {
-        DirectionLight { info, direction }
+        DirectionLight { info, direction, angular_diameter: 0.0, cast_shadows: true }
+    }
+    pub fn with_angular_diameter(mut self, angular_diameter: f32) -> Self {
+        self.angular_diameter = angular_diameter;
+        self
+    }
+    pub fn with_cast_shadows(mut self, cast_shadows: bool) -> Self {
+        self.cast_shadows = cast_shadows;
+        self
     }
     pub fn into_uniform(self) -> DirectionLightUniform {
         DirectionLightUniform {
             color: self.info.color.coords.into(),
             intensity: self.info.intensity,
             direction: self.direction.into(),
+            angular_diameter: self.angular_diameter,
+            cast_shadows: self.cast_shadows as u32,
+            _padding: [0.0; 2],
         }
     }
 }
@@ -26,4 +41,21 @@ pub struct DirectionLightUniform {
     color: mint::Vector4<f32>,
     direction: mint::Vector3<f32>,
     intensity: f32,
+    angular_diameter: f32,
+    cast_shadows: u32,
+    _padding: [f32; 2],
+}
+
+impl DirectionLightUniform {
+    /// Placeholder for the buffer of a scene without direction lights, which can not be empty.
+    pub fn none() -> Self {
+        DirectionLightUniform {
+            color: mint::Vector4 { x: 0.0, y: 0.0, z: 0.0, w: 0.0 },
+            direction: mint::Vector3 { x: 0.0, y: -1.0, z: 0.0 },
+            intensity: 0.0,
+            angular_diameter: 0.0,
+            cast_shadows: 0,
+            _padding: [0.0; 2],
+        }
+    }
 }
@@ -0,0 +1,269 @@
+use std::{fmt, path::Path};
+
+/// Count of vertical angles from 0 to 180 degrees in the table uploaded to shaders.
+pub const IES_TABLE_SIZE: usize = 64;
+
+#[derive(Debug)]
+pub enum IesError {
+    Io(std::io::Error),
+    /// The file has no `TILT=` line which separates the header from the photometric data.
+    MissingTilt,
+    UnexpectedEnd,
+    InvalidNumber(String),
+    InvalidAngles,
+}
+
+impl fmt::Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IesError::Io(e) => write!(f, "cannot read IES file: {}", e),
+            IesError::MissingTilt => f.write_str("IES file has no TILT line"),
+            IesError::UnexpectedEnd => f.write_str("IES file ends before all the data"),
+            IesError::InvalidNumber(s) => write!(f, "invalid number in IES file: {:?}", s),
+            IesError::InvalidAngles => f.write_str("IES file has no or unsorted angles"),
+        }
+    }
+}
+
+impl std::error::Error for IesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IesError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for IesError {
+    fn from(e: std::io::Error) -> Self {
+        IesError::Io(e)
+    }
+}
+
+/// Photometric profile in the IESNA LM-63 format. Vertical angles are measured in degrees from
+/// the direction of the light.
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+    vertical_angles: Vec<f32>,
+    horizontal_angles: Vec<f32>,
+    /// Candela values for every horizontal angle.
+    candela: Vec<Vec<f32>>,
+}
+
+impl IesProfile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IesError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, IesError> {
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find(|l| l.starts_with("TILT="))
+            .ok_or(IesError::MissingTilt)?;
+
+        let mut numbers = lines
+            .flat_map(|l| l.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f32>().map_err(|_| IesError::InvalidNumber(s.to_string())));
+        let mut next = || numbers.next().unwrap_or(Err(IesError::UnexpectedEnd));
+
+        if tilt == "TILT=INCLUDE" {
+            let _lamp_to_luminaire_geometry = next()?;
+            let pairs = next()? as usize;
+            for _ in 0..pairs * 2 {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        // photometric type, units type, width, length, height, ballast factor, future use,
+        // input watts
+        for _ in 0..8 {
+            next()?;
+        }
+
+        let vertical_angles = (0..vertical_count).map(|_| next()).collect::<Result<Vec<_>, _>>()?;
+        let horizontal_angles =
+            (0..horizontal_count).map(|_| next()).collect::<Result<Vec<_>, _>>()?;
+        let candela = (0..horizontal_count)
+            .map(|_| (0..vertical_count).map(|_| Ok(next()? * multiplier)).collect())
+            .collect::<Result<Vec<Vec<_>>, IesError>>()?;
+
+        let sorted = |a: &[f32]| a.windows(2).all(|w| w[0] <= w[1]);
+        if vertical_angles.is_empty()
+            || horizontal_angles.is_empty()
+            || !sorted(&vertical_angles)
+            || !sorted(&horizontal_angles)
+        {
+            return Err(IesError::InvalidAngles);
+        }
+
+        Ok(IesProfile { vertical_angles, horizontal_angles, candela })
+    }
+
+    pub fn vertical_angles(&self) -> &[f32] {
+        &self.vertical_angles
+    }
+
+    pub fn horizontal_angles(&self) -> &[f32] {
+        &self.horizontal_angles
+    }
+
+    /// Candela at the vertical angle in degrees averaged over all horizontal angles.
+    pub fn candela(&self, vertical_angle: f32) -> f32 {
+        let angles = &self.vertical_angles;
+        let i = angles.iter().position(|a| *a > vertical_angle).unwrap_or(angles.len());
+        let value = |row: &Vec<f32>| match i {
+            0 => row[0],
+            i if i == angles.len() => {
+                if vertical_angle > angles[i - 1] {
+                    0.0
+                } else {
+                    row[i - 1]
+                }
+            }
+            i => {
+                let t = (vertical_angle - angles[i - 1]) / (angles[i] - angles[i - 1]);
+                row[i - 1] + (row[i] - row[i - 1]) * t
+            }
+        };
+        self.candela.iter().map(value).sum::<f32>() / self.candela.len() as f32
+    }
+
+    /// Candela from 0 to 180 degrees, normalized so that the maximum is 1.
+    pub fn table(&self) -> Vec<f32> {
+        let table = (0..IES_TABLE_SIZE)
+            .map(|i| self.candela(i as f32 * 180.0 / (IES_TABLE_SIZE - 1) as f32))
+            .collect::<Vec<_>>();
+        let max = table.iter().cloned().fold(0.0, f32::max);
+        if max > 0.0 {
+            table.into_iter().map(|c| c / max).collect()
+        } else {
+            table
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = "IESNA:LM-63-2002
+[TEST] minimal
+[MANUFAC] rencan
+TILT=NONE
+1 1000 1 3 1 1 2 0.0 0.0 0.0
+1.0 1.0 100.0
+0 45 90
+0
+100 50 0
+";
+
+    /// Profile with the vertical angles 0, 90 and the given horizontal angles, the candela of
+    /// every horizontal angle at 0 degrees is its index.
+    fn with_horizontal_angles(angles: &[f32]) -> String {
+        let mut text = format!("TILT=NONE\n1 1000 1 2 {} 1 2 0 0 0 1 1 100\n0 90\n", angles.len());
+        for angle in angles {
+            text += &format!("{} ", angle);
+        }
+        text += "\n";
+        for i in 0..angles.len() {
+            text += &format!("{} 0\n", i);
+        }
+        text
+    }
+
+    #[test]
+    fn parses_minimal_profile() {
+        let profile = IesProfile::parse(MINIMAL).unwrap();
+        assert_eq!(profile.vertical_angles(), &[0.0, 45.0, 90.0]);
+        assert_eq!(profile.horizontal_angles(), &[0.0]);
+        assert_eq!(profile.candela(0.0), 100.0);
+        assert_eq!(profile.candela(22.5), 75.0);
+        assert_eq!(profile.candela(90.0), 0.0);
+        assert_eq!(profile.candela(120.0), 0.0);
+    }
+
+    #[test]
+    fn applies_multiplier() {
+        let text = MINIMAL.replace("1 1000 1 3", "1 1000 2 3");
+        let profile = IesProfile::parse(&text).unwrap();
+        assert_eq!(profile.candela(0.0), 200.0);
+    }
+
+    #[test]
+    fn table_is_normalized() {
+        let table = IesProfile::parse(MINIMAL).unwrap().table();
+        assert_eq!(table.len(), IES_TABLE_SIZE);
+        assert_eq!(table[0], 1.0);
+        assert_eq!(table[IES_TABLE_SIZE - 1], 0.0);
+        assert!(table.iter().all(|c| (0.0..=1.0).contains(c)));
+    }
+
+    #[test]
+    fn skips_included_tilt() {
+        let text = MINIMAL.replace("TILT=NONE\n", "TILT=INCLUDE\n1\n2\n0 90\n1 1\n");
+        let profile = IesProfile::parse(&text).unwrap();
+        assert_eq!(profile.vertical_angles(), &[0.0, 45.0, 90.0]);
+        assert_eq!(profile.candela(0.0), 100.0);
+    }
+
+    #[test]
+    fn averages_symmetric_horizontal_angles() {
+        let cases: [&[f32]; 4] = [
+            // axially symmetric
+            &[0.0],
+            // symmetric in each quadrant
+            &[0.0, 45.0, 90.0],
+            // bilaterally symmetric
+            &[0.0, 90.0, 180.0],
+            // no symmetry
+            &[0.0, 90.0, 180.0, 270.0, 360.0],
+        ];
+        for angles in cases.iter() {
+            let profile = IesProfile::parse(&with_horizontal_angles(angles)).unwrap();
+            assert_eq!(profile.horizontal_angles(), *angles);
+            let average = (angles.len() - 1) as f32 / 2.0;
+            assert_eq!(profile.candela(0.0), average);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(matches!(IesProfile::parse(""), Err(IesError::MissingTilt)));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("TILT=NONE", "")),
+            Err(IesError::MissingTilt)
+        ));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("100 50 0\n", "100 50\n")),
+            Err(IesError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("100 50 0", "100 fifty 0")),
+            Err(IesError::InvalidNumber(s)) if s == "fifty"
+        ));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("0 45 90", "0 90 45")),
+            Err(IesError::InvalidAngles)
+        ));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("1 1000 1 3 1", "1 1000 1 0 1")),
+            Err(IesError::InvalidAngles)
+        ));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("1 1000 1 3 1", "1 1000 1 3 -1")),
+            Err(IesError::InvalidAngles)
+        ));
+        assert!(matches!(
+            IesProfile::parse(&MINIMAL.replace("1 1000 1 3 1", "1 1000 1 3 1e30")),
+            Err(IesError::UnexpectedEnd)
+        ));
+    }
+}
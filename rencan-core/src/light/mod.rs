@@ -1,7 +1,11 @@
 mod direction;
+mod ies;
 mod info;
 mod point;
+mod spot;
 
 pub use direction::{DirectionLight, DirectionLightUniform};
+pub use ies::{IesError, IesProfile, IES_TABLE_SIZE};
 pub use info::LightInfo;
 pub use point::{PointLight, PointLightUniform};
+pub use spot::{SpotLight, SpotLightUniform};
@@ -5,17 +5,25 @@ use nalgebra::Point3;
 pub struct PointLight {
     pub info: LightInfo,
     pub position: Point3<f32>,
+    /// Radius of the spherical light. Zero makes hard shadows.
+    pub radius: f32,
 }
 
 impl PointLight {
     pub fn new(info: LightInfo, position: Point3<f32>) -> Self {
-        PointLight { info, position }
+        PointLight { info, position, radius: 0.0 }
+    }
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
     }
     pub fn into_uniform(self) -> PointLightUniform {
         PointLightUniform {
             color: self.info.color.coords.into(),
             intensity: self.info.intensity,
             position: self.position.coords.into(),
+            radius: self.radius,
+            _padding: [0.0; 3],
         }
     }
 }
@@ -26,4 +34,19 @@ pub struct PointLightUniform {
     color: mint::Vector4<f32>,
     position: mint::Vector3<f32>,
     intensity: f32,
+    radius: f32,
+    _padding: [f32; 3],
+}
+
+impl PointLightUniform {
+    /// Placeholder for the buffer of a scene without point lights, which can not be empty.
+    pub fn none() -> Self {
+        PointLightUniform {
+            color: mint::Vector4 { x: 0.0, y: 0.0, z: 0.0, w: 0.0 },
+            position: mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+            intensity: 0.0,
+            radius: 0.0,
+            _padding: [0.0; 3],
+        }
+    }
 }
@@ -0,0 +1,94 @@
+use crate::light::{ies::IesProfile, info::LightInfo};
+use nalgebra::{Point3, Vector3};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub info: LightInfo,
+    pub position: Point3<f32>,
+    pub direction: Vector3<f32>,
+    /// Half angle in radians of the cone lit with the full intensity.
+    pub inner_angle: f32,
+    /// Half angle in radians of the cone outside of which the light is off.
+    pub outer_angle: f32,
+    /// Radius of the spherical light. Zero makes hard shadows.
+    pub radius: f32,
+    /// Measured distribution of the intensity by the angle from the direction.
+    pub profile: Option<Arc<IesProfile>>,
+}
+
+impl SpotLight {
+    pub fn new(
+        info: LightInfo,
+        position: Point3<f32>,
+        direction: Vector3<f32>,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        SpotLight {
+            info,
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            radius: 0.0,
+            profile: None,
+        }
+    }
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+    pub fn with_profile(mut self, profile: Arc<IesProfile>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+    /// `profile_index` is the index of the profile table in the profiles buffer, `u32::MAX` for
+    /// lights without a profile.
+    pub fn into_uniform(self, profile_index: u32) -> SpotLightUniform {
+        SpotLightUniform {
+            color: self.info.color.coords.into(),
+            position: self.position.coords.into(),
+            intensity: self.info.intensity,
+            direction: self.direction.normalize().into(),
+            cos_inner: self.inner_angle.cos(),
+            // smoothstep in shaders is undefined for equal edges
+            cos_outer: self.outer_angle.cos().min(self.inner_angle.cos() - 1e-4),
+            radius: self.radius,
+            profile_index,
+            _padding: 0.0,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[repr(C, packed)]
+pub struct SpotLightUniform {
+    color: mint::Vector4<f32>,
+    position: mint::Vector3<f32>,
+    intensity: f32,
+    direction: mint::Vector3<f32>,
+    cos_inner: f32,
+    cos_outer: f32,
+    radius: f32,
+    profile_index: u32,
+    _padding: f32,
+}
+
+impl SpotLightUniform {
+    /// Placeholder for the buffer of a scene without spot lights, which can not be empty.
+    pub fn none() -> Self {
+        let zero = mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        SpotLightUniform {
+            color: mint::Vector4 { x: 0.0, y: 0.0, z: 0.0, w: 0.0 },
+            position: zero,
+            intensity: 0.0,
+            direction: zero,
+            cos_inner: 0.0,
+            cos_outer: 0.0,
+            radius: 0.0,
+            profile_index: u32::MAX,
+            _padding: 0.0,
+        }
+    }
+}
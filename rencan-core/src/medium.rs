@@ -0,0 +1,89 @@
+use crevice::std140::AsStd140;
+use nalgebra::Vector3;
+
+/// Homogeneous participating medium filling the whole scene, e.g. fog.
+#[derive(Debug, Clone)]
+pub struct Medium {
+    /// Absorption coefficient per unit of distance for every channel.
+    pub absorption: Vector3<f32>,
+    /// Scattering coefficient per unit of distance for every channel.
+    pub scattering: Vector3<f32>,
+    /// Henyey-Greenstein anisotropy in range (-1; 1). Positive values scatter forward and make
+    /// light shafts brighter when looking towards the light.
+    pub anisotropy: f32,
+    /// Rays are affected by the medium only within this distance, including rays which miss all
+    /// geometry. Negative values are treated as zero, non-finite ones as `f32::MAX`.
+    pub max_distance: f32,
+    /// Count of steps along every ray at which the light scattered towards the ray is sampled.
+    pub steps: u32,
+}
+
+impl Medium {
+    pub fn new(absorption: Vector3<f32>, scattering: Vector3<f32>) -> Self {
+        Medium { absorption, scattering, anisotropy: 0.0, max_distance: 100.0, steps: 8 }
+    }
+    /// Grey fog which mostly scatters the light.
+    pub fn fog(density: f32) -> Self {
+        Medium::new(Vector3::repeat(density * 0.1), Vector3::repeat(density))
+    }
+    pub fn with_anisotropy(mut self, anisotropy: f32) -> Self {
+        self.anisotropy = anisotropy.max(-0.99).min(0.99);
+        self
+    }
+    /// # Panics
+    /// If `max_distance` is not finite or negative.
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        assert!(
+            max_distance.is_finite() && max_distance >= 0.0,
+            "max distance of the medium must be finite and non-negative, got {}",
+            max_distance
+        );
+        self.max_distance = max_distance;
+        self
+    }
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps.max(1);
+        self
+    }
+    /// The public fields may hold any values, so they are clamped to the ranges the shaders
+    /// expect.
+    pub fn into_uniform(self) -> MediumUniform {
+        let max_distance =
+            if self.max_distance.is_finite() { self.max_distance.max(0.0) } else { f32::MAX };
+        MediumUniform {
+            absorption: self.absorption.into(),
+            enabled: 1,
+            scattering: self.scattering.into(),
+            anisotropy: self.anisotropy.max(-0.99).min(0.99),
+            max_distance,
+            steps: self.steps.max(1),
+        }
+    }
+}
+
+#[derive(AsStd140)]
+pub struct MediumUniform {
+    absorption: mint::Vector3<f32>,
+    enabled: u32,
+    scattering: mint::Vector3<f32>,
+    anisotropy: f32,
+    max_distance: f32,
+    steps: u32,
+}
+
+impl MediumUniform {
+    /// Uniform of a scene without a medium.
+    pub fn none() -> Self {
+        let zero = mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        MediumUniform {
+            absorption: zero,
+            enabled: 0,
+            scattering: zero,
+            anisotropy: 0.0,
+            max_distance: 0.0,
+            steps: 1,
+        }
+    }
+}
+
+pub type MediumUniformStd140 = <MediumUniform as AsStd140>::Std140Type;
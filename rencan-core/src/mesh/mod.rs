@@ -0,0 +1,110 @@
+use nalgebra::{Point2, Point3, Point4, Vector3};
+
+use crate::{model::AppModel, Model};
+
+pub mod primitives;
+
+/// Indexed triangle mesh with per-vertex normals and texture coordinates. Triangles are wound
+/// so that `cross(b - a, c - a)` points out of the front face.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub positions: Vec<Point3<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub uvs: Vec<Point2<f32>>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Mesh::default()
+    }
+
+    pub fn into_model(self) -> Model {
+        self.into()
+    }
+
+    pub fn into_app_model(self) -> AppModel {
+        AppModel::new(self.into())
+    }
+
+    fn push_vertex(&mut self, position: Point3<f32>, normal: Vector3<f32>, uv: Point2<f32>) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        self.positions.len() as u32 - 1
+    }
+
+    /// Winds the triangle by the vertex normals. Degenerate triangles are skipped.
+    fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+        let p = |i: u32| self.positions[i as usize];
+        let face_normal = (p(b) - p(a)).cross(&(p(c) - p(a)));
+        if face_normal.norm_squared() <= f32::EPSILON * f32::EPSILON {
+            return;
+        }
+        let vertex_normal =
+            self.normals[a as usize] + self.normals[b as usize] + self.normals[c as usize];
+        if face_normal.dot(&vertex_normal) < 0.0 {
+            self.indices.push([a, c, b]);
+        } else {
+            self.indices.push([a, b, c]);
+        }
+    }
+
+    /// Adds a grid of `u_segments` x `v_segments` quads over the surface `f(u, v)` returning
+    /// the position and the normal for `u` and `v` in range [0; 1].
+    fn push_parametric(
+        &mut self,
+        u_segments: u32,
+        v_segments: u32,
+        f: impl Fn(f32, f32) -> (Point3<f32>, Vector3<f32>),
+    ) {
+        let u_segments = u_segments.max(1);
+        let v_segments = v_segments.max(1);
+        let base = self.positions.len() as u32;
+
+        for j in 0..=v_segments {
+            for i in 0..=u_segments {
+                let (u, v) = (i as f32 / u_segments as f32, j as f32 / v_segments as f32);
+                let (position, normal) = f(u, v);
+                self.push_vertex(position, normal.normalize(), Point2::new(u, v));
+            }
+        }
+
+        let row = u_segments + 1;
+        for j in 0..v_segments {
+            for i in 0..u_segments {
+                let a = base + j * row + i;
+                let (b, c, d) = (a + 1, a + row, a + row + 1);
+                self.push_triangle(a, b, d);
+                self.push_triangle(a, d, c);
+            }
+        }
+    }
+}
+
+impl From<Mesh> for Model {
+    fn from(mesh: Mesh) -> Self {
+        Model::new(
+            mesh.positions.iter().map(|p| Point4::new(p.x, p.y, p.z, 0.0)).collect(),
+            mesh.indices.iter().map(|i| Point4::new(i[0], i[1], i[2], 0)).collect(),
+        )
+        .with_normals(mesh.normals)
+        .with_uvs(mesh.uvs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_model_keeps_attributes() {
+        let mesh = primitives::plane(1.0, 1.0, 1, 1);
+        let model = mesh.clone().into_model();
+        assert_eq!(model.vertices.len(), mesh.positions.len());
+        assert_eq!(model.normals, mesh.normals);
+        assert_eq!(model.uvs, mesh.uvs);
+        assert_eq!(model.indexes.len(), mesh.indices.len());
+        assert_eq!(model.validate(), Ok(()));
+    }
+}
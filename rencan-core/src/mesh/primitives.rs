@@ -0,0 +1,328 @@
+//! Generators of meshes centered at the origin. Segment counts are clamped to the minimum which
+//! still makes a closed surface.
+
+use std::{collections::HashMap, f32::consts::PI};
+
+use nalgebra::{Point2, Point3, Vector3};
+
+use crate::mesh::Mesh;
+
+/// Axis aligned cube with the edge `size`, every face is split into `subdivisions` x
+/// `subdivisions` quads.
+pub fn cube(size: f32, subdivisions: u32) -> Mesh {
+    let half = size * 0.5;
+    let faces: [(Vector3<f32>, Vector3<f32>, Vector3<f32>); 6] = [
+        (Vector3::x(), Vector3::z(), Vector3::y()),
+        (-Vector3::x(), Vector3::y(), Vector3::z()),
+        (Vector3::y(), Vector3::x(), Vector3::z()),
+        (-Vector3::y(), Vector3::z(), Vector3::x()),
+        (Vector3::z(), Vector3::y(), Vector3::x()),
+        (-Vector3::z(), Vector3::x(), Vector3::y()),
+    ];
+
+    let mut mesh = Mesh::new();
+    for (normal, tangent, bitangent) in faces.iter() {
+        mesh.push_parametric(subdivisions, subdivisions, |u, v| {
+            let offset = tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0);
+            (Point3::from((normal + offset) * half), *normal)
+        });
+    }
+    mesh
+}
+
+/// Sphere split into `segments` meridians and `rings` parallels.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let mut mesh = Mesh::new();
+    mesh.push_parametric(segments.max(3), rings.max(2), |u, v| {
+        let normal = spherical(u * 2.0 * PI, v * PI);
+        (Point3::from(normal * radius), normal)
+    });
+    mesh
+}
+
+/// Sphere made by splitting every triangle of an icosahedron into four `subdivisions` times.
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut points = vec![
+        Vector3::new(-1.0, t, 0.0),
+        Vector3::new(1.0, t, 0.0),
+        Vector3::new(-1.0, -t, 0.0),
+        Vector3::new(1.0, -t, 0.0),
+        Vector3::new(0.0, -1.0, t),
+        Vector3::new(0.0, 1.0, t),
+        Vector3::new(0.0, -1.0, -t),
+        Vector3::new(0.0, 1.0, -t),
+        Vector3::new(t, 0.0, -1.0),
+        Vector3::new(t, 0.0, 1.0),
+        Vector3::new(-t, 0.0, -1.0),
+        Vector3::new(-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|p| p.normalize())
+    .collect::<Vec<_>>();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut middles = HashMap::new();
+        let mut middle = |a: u32, b: u32, points: &mut Vec<Vector3<f32>>| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let point = (points[a as usize] + points[b as usize]).normalize();
+                points.push(point);
+                points.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let ab = middle(a, b, &mut points);
+                let bc = middle(b, c, &mut points);
+                let ca = middle(c, a, &mut points);
+                vec![[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut mesh = Mesh::new();
+    for normal in points {
+        let uv = Point2::new(
+            normal.z.atan2(normal.x) / (2.0 * PI) + 0.5,
+            normal.y.max(-1.0).min(1.0).acos() / PI,
+        );
+        mesh.push_vertex(Point3::from(normal * radius), normal, uv);
+    }
+    for [a, b, c] in triangles {
+        mesh.push_triangle(a, b, c);
+    }
+    mesh
+}
+
+/// Capped cylinder along the `y` axis.
+pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> Mesh {
+    let segments = segments.max(3);
+    let half = height * 0.5;
+
+    let mut mesh = Mesh::new();
+    mesh.push_parametric(segments, height_segments, |u, v| {
+        let normal = radial(u * 2.0 * PI);
+        (Point3::new(normal.x * radius, v * height - half, normal.z * radius), normal)
+    });
+    push_cap(&mut mesh, radius, half, segments, Vector3::y());
+    push_cap(&mut mesh, radius, -half, segments, -Vector3::y());
+    mesh
+}
+
+/// Cone along the `y` axis with the apex at the top and a capped base.
+pub fn cone(radius: f32, height: f32, segments: u32, height_segments: u32) -> Mesh {
+    let segments = segments.max(3);
+    let half = height * 0.5;
+
+    let mut mesh = Mesh::new();
+    mesh.push_parametric(segments, height_segments, |u, v| {
+        let direction = radial(u * 2.0 * PI);
+        let position = direction * radius * (1.0 - v);
+        let normal = Vector3::new(direction.x * height, radius, direction.z * height);
+        (Point3::new(position.x, v * height - half, position.z), normal)
+    });
+    push_cap(&mut mesh, radius, -half, segments, -Vector3::y());
+    mesh
+}
+
+/// Torus around the `y` axis, `major_radius` is the distance from the center to the center
+/// of the tube.
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> Mesh {
+    let mut mesh = Mesh::new();
+    mesh.push_parametric(major_segments.max(3), minor_segments.max(3), |u, v| {
+        let direction = radial(u * 2.0 * PI);
+        let (sin, cos) = (v * 2.0 * PI).sin_cos();
+        let normal = direction * cos + Vector3::y() * sin;
+        (Point3::from(direction * major_radius + normal * minor_radius), normal)
+    });
+    mesh
+}
+
+/// Plane `y = 0` facing `+y`, split into `subdivisions_x` x `subdivisions_z` quads.
+pub fn plane(width: f32, depth: f32, subdivisions_x: u32, subdivisions_z: u32) -> Mesh {
+    let mut mesh = Mesh::new();
+    mesh.push_parametric(subdivisions_x, subdivisions_z, |u, v| {
+        (Point3::new((u - 0.5) * width, 0.0, (v - 0.5) * depth), Vector3::y())
+    });
+    mesh
+}
+
+/// Cylinder along the `y` axis with hemispheres on the ends, `height` does not include them.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let segments = segments.max(3);
+    let rings = rings.max(1);
+    let half = height * 0.5;
+
+    let mut mesh = Mesh::new();
+    let hemisphere = |mesh: &mut Mesh, from: f32, center: f32| {
+        mesh.push_parametric(segments, rings, |u, v| {
+            let normal = spherical(u * 2.0 * PI, from + v * PI * 0.5);
+            (Point3::new(0.0, center, 0.0) + normal * radius, normal)
+        });
+    };
+    hemisphere(&mut mesh, 0.0, half);
+    mesh.push_parametric(segments, 1, |u, v| {
+        let normal = radial(u * 2.0 * PI);
+        (Point3::new(normal.x * radius, half - v * height, normal.z * radius), normal)
+    });
+    hemisphere(&mut mesh, PI * 0.5, -half);
+    mesh
+}
+
+/// Unit vector by the azimuth around `y` and the polar angle from `+y`.
+fn spherical(azimuth: f32, polar: f32) -> Vector3<f32> {
+    // `sin(PI)` is not zero in floats, the south pole must still be a single point so that
+    // the triangles around it collapse
+    if polar >= PI {
+        return -Vector3::y();
+    }
+    let (sin_polar, cos_polar) = polar.sin_cos();
+    Vector3::new(sin_polar * azimuth.cos(), cos_polar, sin_polar * azimuth.sin())
+}
+
+/// Unit vector in the plane `y = 0` by the azimuth around `y`.
+fn radial(azimuth: f32) -> Vector3<f32> {
+    Vector3::new(azimuth.cos(), 0.0, azimuth.sin())
+}
+
+fn push_cap(mesh: &mut Mesh, radius: f32, y: f32, segments: u32, normal: Vector3<f32>) {
+    mesh.push_parametric(segments, 1, |u, v| {
+        let position = radial(u * 2.0 * PI) * radius * v;
+        (Point3::new(position.x, y, position.z), normal)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the invariants of every generated mesh and returns the counts of vertices and
+    /// triangles.
+    fn check(mesh: &Mesh) -> (usize, usize) {
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        assert_eq!(mesh.uvs.len(), mesh.positions.len());
+        for triangle in &mesh.indices {
+            assert!(triangle.iter().all(|&i| (i as usize) < mesh.positions.len()));
+            let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+            let p = &mesh.positions;
+            let face_normal = (p[b] - p[a]).cross(&(p[c] - p[a]));
+            let vertex_normal = mesh.normals[a] + mesh.normals[b] + mesh.normals[c];
+            assert!(face_normal.norm() > 0.0);
+            assert!(
+                face_normal.dot(&vertex_normal) > 0.0,
+                "triangle {:?} is wound inwards",
+                triangle
+            );
+        }
+        (mesh.positions.len(), mesh.indices.len())
+    }
+
+    /// Triangles of convex meshes centered at the origin face away from it.
+    fn check_outwards(mesh: &Mesh) {
+        for triangle in &mesh.indices {
+            let p = |k: usize| mesh.positions[triangle[k] as usize];
+            let (a, b, c) = (p(0), p(1), p(2));
+            let centroid = (a.coords + b.coords + c.coords) / 3.0;
+            assert!((b - a).cross(&(c - a)).dot(&centroid) > 0.0);
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let mesh = super::cube(2.0, 3);
+        assert_eq!(check(&mesh), (6 * 4 * 4, 6 * 2 * 3 * 3));
+        check_outwards(&mesh);
+        assert!(mesh.positions.iter().all(|p| p.coords.amax() == 1.0));
+    }
+
+    #[test]
+    fn uv_sphere() {
+        let mesh = super::uv_sphere(2.0, 8, 4);
+        // the triangles touching the poles have one edge collapsed into a point
+        assert_eq!(check(&mesh), (9 * 5, 2 * 8 * 4 - 2 * 8));
+        check_outwards(&mesh);
+        assert!(mesh.positions.iter().all(|p| (p.coords.norm() - 2.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn icosphere() {
+        for subdivisions in 0..3 {
+            let mesh = super::icosphere(1.0, subdivisions);
+            let faces = 20 * 4usize.pow(subdivisions);
+            assert_eq!(check(&mesh), (faces / 2 + 2, faces));
+            check_outwards(&mesh);
+        }
+    }
+
+    #[test]
+    fn cylinder() {
+        let mesh = super::cylinder(1.0, 2.0, 6, 2);
+        // the side and two caps, whose inner triangles collapse into the center
+        assert_eq!(check(&mesh), (7 * 3 + 2 * 7 * 2, 2 * 6 * 2 + 2 * 6));
+        check_outwards(&mesh);
+    }
+
+    #[test]
+    fn cone() {
+        let mesh = super::cone(1.0, 2.0, 6, 2);
+        // the side collapses into the apex and the cap into its center
+        assert_eq!(check(&mesh), (7 * 3 + 7 * 2, 2 * 6 * 2 - 6 + 6));
+        check_outwards(&mesh);
+    }
+
+    #[test]
+    fn torus() {
+        let mesh = super::torus(2.0, 0.5, 8, 6);
+        assert_eq!(check(&mesh), (9 * 7, 2 * 8 * 6));
+    }
+
+    #[test]
+    fn plane() {
+        let mesh = super::plane(2.0, 1.0, 4, 2);
+        assert_eq!(check(&mesh), (5 * 3, 2 * 4 * 2));
+        assert!(mesh.normals.iter().all(|n| *n == Vector3::y()));
+    }
+
+    #[test]
+    fn capsule() {
+        let mesh = super::capsule(1.0, 2.0, 6, 3);
+        // two hemispheres collapsing into the poles and the side between them
+        assert_eq!(check(&mesh), (2 * 7 * 4 + 7 * 2, 2 * (2 * 6 * 3 - 6) + 2 * 6));
+        check_outwards(&mesh);
+    }
+
+    #[test]
+    fn clamps_segments() {
+        assert_eq!(check(&super::cube(1.0, 0)), (6 * 4, 6 * 2));
+        assert_eq!(check(&super::uv_sphere(1.0, 0, 0)).1, 2 * 3 * 2 - 2 * 3);
+        assert_eq!(check(&super::torus(1.0, 0.5, 0, 0)).1, 2 * 3 * 3);
+    }
+}
@@ -1,16 +1,140 @@
-use crate::hitbox::HitBoxRectangle;
+use crate::{
+    emission::Emission,
+    handle::Handle,
+    hitbox::HitBoxRectangle,
+    node::NodeHandle,
+    primitive::{Primitive, NO_PRIMITIVE},
+};
 use crevice::std140::AsStd140;
-use nalgebra::{Isometry3, Point3, Point4, Translation3, UnitQuaternion};
+use nalgebra::{
+    Affine3, Isometry3, Matrix3, Matrix4, Point2, Point3, Point4, Rotation3, Translation3,
+    UnitQuaternion, Vector3, U1, U3,
+};
+use std::{fmt, ops::BitOr};
+
+/// Set of ray kinds which hit a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Visibility(u32);
+
+impl Visibility {
+    // Must match VISIBLE_* in defs.glsl
+    pub const NONE: Visibility = Visibility(0);
+    /// Primary rays from the camera.
+    pub const CAMERA: Visibility = Visibility(1);
+    /// Rays towards lights and ambient occlusion rays.
+    pub const SHADOW: Visibility = Visibility(2);
+    pub const REFLECTION: Visibility = Visibility(4);
+    pub const ALL: Visibility = Visibility(7);
+
+    pub fn contains(self, other: Visibility) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl BitOr for Visibility {
+    type Output = Visibility;
+
+    fn bitor(self, rhs: Visibility) -> Visibility {
+        Visibility(self.0 | rhs.0)
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility::ALL
+    }
+}
+
+/// Faces of triangles which camera and reflection rays pass through. The front face is the one
+/// with counter-clockwise vertices. Shadow rays hit both faces, so single-sided walls still
+/// cast shadows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    /// Double-sided triangles.
+    None,
+    Back,
+    Front,
+}
+
+impl CullMode {
+    /// Value of the mode in shaders, must match CULL_* in defs.glsl.
+    pub fn into_uniform(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Default for CullMode {
+    fn default() -> Self {
+        CullMode::Back
+    }
+}
+
+/// Problem in the geometry of a model which would make the GPU read garbage or break hit boxes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    /// The model has neither triangles nor a primitive.
+    Empty,
+    NonFiniteVertex {
+        vertex: usize,
+    },
+    IndexOutOfBounds {
+        triangle: usize,
+        index: u32,
+    },
+    DegenerateTriangle {
+        triangle: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::Empty => f.write_str("mesh has no triangles"),
+            MeshError::NonFiniteVertex { vertex } => {
+                write!(f, "vertex {} has a NaN or infinite coordinate", vertex)
+            }
+            MeshError::IndexOutOfBounds { triangle, index } => {
+                write!(f, "triangle {} refers to the missing vertex {}", triangle, index)
+            }
+            MeshError::DegenerateTriangle { triangle } => {
+                write!(f, "triangle {} has zero area", triangle)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
 
 #[derive(Debug, Clone)]
 pub struct Model {
     pub vertices: Vec<Point4<f32>>,
     pub indexes: Vec<Point4<u32>>,
+    /// Normals of the vertices in the model space, interpolated over triangles for smooth
+    /// shading. Empty for flat shaded models, vertices without a normal are shaded flat.
+    pub normals: Vec<Vector3<f32>>,
+    /// Texture coordinates of the vertices, empty if the model has none.
+    pub uvs: Vec<Point2<f32>>,
     pub rotation: UnitQuaternion<f32>,
     pub position: Point3<f32>,
-    pub scaling: f32,
+    /// Scale along the axes of the model, applied before the rotation.
+    pub scaling: Vector3<f32>,
+    /// Arbitrary transform to the space of the node, replaces `rotation`, `position` and
+    /// `scaling`.
+    pub transform: Option<Affine3<f32>>,
+    /// Transform to the space of the node at the closing of the shutter. The model moves from
+    /// its transform to this one during a motion blurred frame, the node stays still.
+    pub end_transform: Option<Affine3<f32>>,
     pub albedo: f32,
     pub specularity: f32,
+    pub emission: Option<Emission>,
+    /// Analytic surface of the model. Models with a primitive have no vertices and indexes.
+    pub primitive: Option<Primitive>,
+    pub visibility: Visibility,
+    pub cull_mode: CullMode,
 }
 
 impl Model {
@@ -18,67 +142,364 @@ impl Model {
         Model {
             vertices,
             indexes,
+            normals: vec![],
+            uvs: vec![],
             rotation: UnitQuaternion::from_euler_angles(0.0, 0.0, 0.0),
             position: Point3::new(0.0, 0.0, 0.0),
-            scaling: 1.0,
+            scaling: Vector3::repeat(1.0),
+            transform: None,
+            end_transform: None,
             albedo: 0.18,
             specularity: 0.0,
+            emission: None,
+            primitive: None,
+            visibility: Visibility::ALL,
+            cull_mode: CullMode::Back,
         }
     }
+    pub fn from_primitive(primitive: Primitive) -> Self {
+        Model { primitive: Some(primitive), ..Model::new(vec![], vec![]) }
+    }
     pub fn with_isometry(
         vertices: Vec<Point4<f32>>,
         indexes: Vec<Point4<u32>>,
         rotation: UnitQuaternion<f32>,
         position: Point3<f32>,
-        scaling: f32,
+        scaling: Vector3<f32>,
         albedo: f32,
         specularity: f32,
     ) -> Self {
-        Model { vertices, indexes, rotation, position, scaling, albedo, specularity }
+        Model {
+            vertices,
+            indexes,
+            normals: vec![],
+            uvs: vec![],
+            rotation,
+            position,
+            scaling,
+            transform: None,
+            end_transform: None,
+            albedo,
+            specularity,
+            emission: None,
+            primitive: None,
+            visibility: Visibility::ALL,
+            cull_mode: CullMode::Back,
+        }
+    }
+    pub fn with_normals(mut self, normals: Vec<Vector3<f32>>) -> Self {
+        self.normals = normals;
+        self
+    }
+    pub fn with_uvs(mut self, uvs: Vec<Point2<f32>>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+    pub fn with_emission(mut self, emission: Emission) -> Self {
+        self.emission = Some(emission);
+        self
+    }
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+    pub fn isometry(&self) -> Isometry3<f32> {
+        Isometry3::from_parts(
+            Translation3::new(self.position.x, self.position.y, self.position.z),
+            self.rotation,
+        )
+    }
+    pub fn with_transform(mut self, transform: Affine3<f32>) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+    pub fn with_end_transform(mut self, end_transform: Affine3<f32>) -> Self {
+        self.end_transform = Some(end_transform);
+        self
+    }
+    /// Transform from the model space to the space of the node.
+    pub fn local_matrix(&self) -> Matrix4<f32> {
+        match &self.transform {
+            Some(transform) => transform.to_homogeneous(),
+            None => {
+                self.isometry().to_homogeneous() * Matrix4::new_nonuniform_scaling(&self.scaling)
+            }
+        }
+    }
+    /// Triangles of the model in the world space, `parent` is the world transform of the node
+    /// of the model.
+    pub fn world_triangles(
+        &self,
+        parent: &Isometry3<f32>,
+    ) -> impl Iterator<Item = [Point3<f32>; 3]> + '_ {
+        let matrix = parent.to_homogeneous() * self.local_matrix();
+        let vertex = move |i: u32| {
+            let v = self.vertices[i as usize];
+            matrix.transform_point(&Point3::new(v.x, v.y, v.z))
+        };
+        self.indexes.iter().map(move |i| [vertex(i.x), vertex(i.y), vertex(i.z)])
+    }
+    /// Area in the world space, see [`Primitive::surface_area`] for models with a primitive.
+    pub fn surface_area(&self) -> f32 {
+        match &self.primitive {
+            Some(primitive) => primitive.surface_area(&linear_part(&self.local_matrix())),
+            None => self.world_triangles(&Isometry3::identity()).map(|t| triangle_area(&t)).sum(),
+        }
     }
-    pub fn get_uniform_info(&self, model_id: u32) -> ModelUniformInfo {
+    /// Returns the first problem in the geometry. Models with a primitive have no triangles and
+    /// are always valid.
+    pub fn validate(&self) -> Result<(), MeshError> {
+        if self.primitive.is_some() {
+            return Ok(());
+        }
+        if self.vertices.is_empty() || self.indexes.is_empty() {
+            return Err(MeshError::Empty);
+        }
+        if let Some(vertex) = self.vertices.iter().position(|v| !is_finite(v)) {
+            return Err(MeshError::NonFiniteVertex { vertex });
+        }
+        (0..self.indexes.len()).try_for_each(|triangle| self.check_triangle(triangle))
+    }
+    /// Drops triangles which refer to missing or non-finite vertices or have zero area, then
+    /// drops the vertices which no triangle refers to and renumbers the rest, so that the model
+    /// passes [`Model::validate`]. Returns the count of dropped triangles or
+    /// [`MeshError::Empty`] if no triangle is left. Models with a primitive are left as is.
+    pub fn repair(&mut self) -> Result<usize, MeshError> {
+        if self.primitive.is_some() {
+            return Ok(0);
+        }
+        let before = self.indexes.len();
+        let valid = (0..before).map(|i| self.check_triangle(i).is_ok()).collect::<Vec<_>>();
+        let mut valid = valid.into_iter();
+        self.indexes.retain(|_| valid.next().unwrap());
+
+        let mut used = vec![false; self.vertices.len()];
+        for index in &self.indexes {
+            for &i in [index.x, index.y, index.z].iter() {
+                used[i as usize] = true;
+            }
+        }
+        let mut remap = vec![0; used.len()];
+        let mut count = 0;
+        for (new, &used) in remap.iter_mut().zip(used.iter()) {
+            *new = count;
+            count += used as u32;
+        }
+        // Normals and uvs may be shorter than the vertices, their prefixes stay aligned.
+        let mut used_vertex = used.iter().cloned();
+        self.vertices.retain(|_| used_vertex.next().unwrap());
+        let mut used_normal = used.iter().cloned();
+        self.normals.retain(|_| used_normal.next().unwrap_or(false));
+        let mut used_uv = used.iter().cloned();
+        self.uvs.retain(|_| used_uv.next().unwrap_or(false));
+        for index in &mut self.indexes {
+            *index = Point4::new(
+                remap[index.x as usize],
+                remap[index.y as usize],
+                remap[index.z as usize],
+                index.w,
+            );
+        }
+
+        if self.indexes.is_empty() {
+            return Err(MeshError::Empty);
+        }
+        Ok(before - self.indexes.len())
+    }
+    fn check_triangle(&self, triangle: usize) -> Result<(), MeshError> {
+        let index = self.indexes[triangle];
+        let mut points = [Point3::origin(); 3];
+        for (point, &i) in points.iter_mut().zip([index.x, index.y, index.z].iter()) {
+            let v = self
+                .vertices
+                .get(i as usize)
+                .ok_or(MeshError::IndexOutOfBounds { triangle, index: i })?;
+            if !is_finite(v) {
+                return Err(MeshError::NonFiniteVertex { vertex: i as usize });
+            }
+            *point = Point3::new(v.x, v.y, v.z);
+        }
+        let (a, b) = (points[1] - points[0], points[2] - points[0]);
+        // relative to the edges, so that small but well shaped triangles pass
+        if a.cross(&b).norm_squared() <= f32::EPSILON * a.norm_squared() * b.norm_squared() {
+            return Err(MeshError::DegenerateTriangle { triangle });
+        }
+        Ok(())
+    }
+    fn emission_radiance(&self) -> Vector3<f32> {
+        match &self.emission {
+            Some(emission) => emission.radiance(self.surface_area()),
+            None => Vector3::zeros(),
+        }
+    }
+    /// `primitive_idx` is the index of the model primitive in the primitives buffer, it is
+    /// ignored for triangle meshes. `parent` is the world transform of the node of the model.
+    pub fn get_uniform_info(
+        &self,
+        handle: ModelHandle,
+        primitive_idx: u32,
+        parent: &Isometry3<f32>,
+    ) -> ModelUniformInfo {
+        let model_to_world = parent.to_homogeneous() * self.local_matrix();
+        let (world_to_model, normal_to_world) = inverse_transforms(&model_to_world);
+        let start = Decomposed::new(&model_to_world);
+        let end = match &self.end_transform {
+            Some(end) => Decomposed::new(&(parent.to_homogeneous() * end.to_homogeneous())),
+            None => start.clone(),
+        };
         ModelUniformInfo {
-            isometry: (Isometry3::from_parts(
-                Translation3::new(self.position.x, self.position.y, self.position.z),
-                self.rotation,
-            )
-            .to_matrix()
-                * self.scaling)
-                .into(),
-            model_id,
+            model_to_world: model_to_world.into(),
+            world_to_model: world_to_model.into(),
+            normal_to_world: normal_to_world.into(),
+            start_translation: start.translation.into(),
+            start_rotation: start.rotation.into_inner().coords.into(),
+            start_stretch: start.stretch.into(),
+            end_translation: end.translation.into(),
+            end_rotation: end.rotation.into_inner().coords.into(),
+            end_stretch: end.stretch.into(),
+            moving: self.end_transform.is_some() as u32,
+            model_id: handle.to_raw(),
             vertices_length: self.vertices.len() as u32,
             indexes_length: self.indexes.len() as u32,
             albedo: self.albedo,
             specularity: self.specularity,
-            offsets: mint::Vector2 {
-                x: 0.0,
-                y: 0.0,
-            }
+            visibility: self.visibility.bits(),
+            cull_mode: self.cull_mode.into_uniform(),
+            emission: self.emission_radiance().into(),
+            primitive_idx: if self.primitive.is_some() { primitive_idx } else { NO_PRIMITIVE },
+        }
+    }
+}
+
+/// `world_to_model` and `normal_to_world` of the `model_to_world` transform.
+fn inverse_transforms(model_to_world: &Matrix4<f32>) -> (Matrix4<f32>, Matrix3<f32>) {
+    // Singular transforms flatten the model, zero matrices make it impossible to hit.
+    let world_to_model = model_to_world.try_inverse().unwrap_or_else(Matrix4::zeros);
+    let normal_to_world = linear_part(model_to_world)
+        .try_inverse()
+        .map(|inverse| inverse.transpose())
+        .unwrap_or_else(Matrix3::zeros);
+    (world_to_model, normal_to_world)
+}
+
+/// Affine transform split into `translation * rotation * stretch`, the stretch is symmetric and
+/// includes the scaling, the shear and the reflection. Motion blurred models interpolate the
+/// parts separately, so that they do not shrink while rotating.
+#[derive(Debug, Clone)]
+struct Decomposed {
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    stretch: Matrix3<f32>,
+}
+
+impl Decomposed {
+    fn new(transform: &Matrix4<f32>) -> Self {
+        let translation = transform.fixed_slice::<U3, U1>(0, 3).into_owned();
+        // polar decomposition by the SVD
+        let svd = linear_part(transform).svd(true, true);
+        let (mut u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let mut sigma = svd.singular_values;
+        // a reflection goes to the stretch, the rotation must be proper
+        if (u * v_t).determinant() < 0.0 {
+            u.column_mut(2).neg_mut();
+            sigma[2] = -sigma[2];
+        }
+        let rotation = Rotation3::from_matrix_unchecked(u * v_t);
+        Decomposed {
+            translation,
+            rotation: UnitQuaternion::from_rotation_matrix(&rotation),
+            stretch: v_t.transpose() * Matrix3::from_diagonal(&sigma) * v_t,
         }
     }
 }
 
+fn linear_part(matrix: &Matrix4<f32>) -> Matrix3<f32> {
+    matrix.fixed_slice::<U3, U3>(0, 0).into_owned()
+}
+
+fn is_finite(v: &Point4<f32>) -> bool {
+    v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+}
+
+pub(crate) fn triangle_area(t: &[Point3<f32>; 3]) -> f32 {
+    (t[1] - t[0]).cross(&(t[2] - t[0])).norm() * 0.5
+}
+
 #[derive(AsStd140)]
 pub struct ModelUniformInfo {
-    pub isometry: mint::ColumnMatrix4<f32>,
+    pub model_to_world: mint::ColumnMatrix4<f32>,
+    pub world_to_model: mint::ColumnMatrix4<f32>,
+    /// Inverse transpose of the linear part of `model_to_world`.
+    pub normal_to_world: mint::ColumnMatrix3<f32>,
+    /// `model_to_world` split into a translation, a rotation quaternion and a stretch at the
+    /// opening and the closing of the shutter. Used instead of the transforms above only by
+    /// moving models.
+    pub start_translation: mint::Vector3<f32>,
+    pub start_rotation: mint::Vector4<f32>,
+    pub start_stretch: mint::ColumnMatrix3<f32>,
+    pub end_translation: mint::Vector3<f32>,
+    pub end_rotation: mint::Vector4<f32>,
+    pub end_stretch: mint::ColumnMatrix3<f32>,
+    /// 1 if the model has an end transform.
+    pub moving: u32,
+    /// Raw [`ModelHandle`] of the model.
     pub model_id: u32,
     pub vertices_length: u32,
     pub indexes_length: u32,
     pub albedo: f32,
     pub specularity: f32,
-    pub offsets: mint::Vector2<f32>,
+    pub visibility: u32,
+    pub cull_mode: u32,
+    /// Radiance of the front faces.
+    pub emission: mint::Vector3<f32>,
+    pub primitive_idx: u32,
 }
 
 impl ModelUniformInfo {
+    /// Placeholder for the buffer of a scene without visible models, which can not be empty.
+    pub fn none() -> Self {
+        let zero3 = mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        let rotation = mint::Vector4 { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+        ModelUniformInfo {
+            model_to_world: Matrix4::zeros().into(),
+            world_to_model: Matrix4::zeros().into(),
+            normal_to_world: Matrix3::zeros().into(),
+            start_translation: zero3,
+            start_rotation: rotation,
+            start_stretch: Matrix3::zeros().into(),
+            end_translation: zero3,
+            end_rotation: rotation,
+            end_stretch: Matrix3::zeros().into(),
+            moving: 0,
+            model_id: 0,
+            vertices_length: 0,
+            indexes_length: 0,
+            albedo: 0.0,
+            specularity: 0.0,
+            visibility: 0,
+            cull_mode: 0,
+            emission: zero3,
+            primitive_idx: NO_PRIMITIVE,
+        }
+    }
     pub fn as_std140(&self) -> <Self as AsStd140>::Std140Type {
         AsStd140::as_std140(self)
     }
 }
 
+/// Stable id of a model in a [`Scene`](crate::Scene), reported by intersections, picking and
+/// the object id AOV.
+pub type ModelHandle = Handle<AppModel>;
+
 #[allow(dead_code)]
 pub struct AppModel {
     model: Model,
     hit_box: HitBoxRectangle,
+    pub(crate) node: Option<NodeHandle>,
     need_update_info: bool,
     need_update_vertices: bool,
     need_update_indices: bool,
@@ -94,14 +515,29 @@ impl AppModel {
     pub fn new(model: Model) -> Self {
         let mut hit_box = HitBoxRectangle::new();
         model.vertices.iter().for_each(|v| hit_box.update_by_point(&Point3::new(v.x, v.y, v.z)));
+        // Primitives are not tested against the hit box, but it is still useful for the bounds.
+        if let Some((min, max)) = model.primitive.as_ref().and_then(Primitive::bounds) {
+            hit_box.update_by_point(&min);
+            hit_box.update_by_point(&max);
+        }
         Self {
             model,
             hit_box,
+            node: None,
             need_update_info: true,
             need_update_vertices: true,
             need_update_indices: true,
         }
     }
+    /// Same as [`AppModel::new`], but rejects models which do not pass [`Model::validate`].
+    pub fn try_new(model: Model) -> Result<Self, MeshError> {
+        model.validate()?;
+        Ok(Self::new(model))
+    }
+    /// Node of the scene which the model belongs to.
+    pub fn node(&self) -> Option<NodeHandle> {
+        self.node
+    }
     pub fn model(&self) -> &Model {
         &self.model
     }
@@ -110,3 +546,138 @@ impl AppModel {
         self.need_update_info = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn quad() -> Model {
+        Model::new(
+            vec![
+                Point4::new(0.0, 0.0, 0.0, 0.0),
+                Point4::new(1.0, 0.0, 0.0, 0.0),
+                Point4::new(1.0, 1.0, 0.0, 0.0),
+                Point4::new(0.0, 1.0, 0.0, 0.0),
+            ],
+            vec![Point4::new(0, 1, 2, 0), Point4::new(0, 2, 3, 0)],
+        )
+    }
+
+    #[test]
+    fn valid_model() {
+        assert_eq!(quad().validate(), Ok(()));
+        assert_eq!(Model::from_primitive(Primitive::Sphere { radius: 1.0 }).validate(), Ok(()));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Model::new(vec![], vec![]).validate(), Err(MeshError::Empty));
+        assert_eq!(Model { indexes: vec![], ..quad() }.validate(), Err(MeshError::Empty));
+    }
+
+    #[test]
+    fn non_finite_vertex() {
+        let mut model = quad();
+        model.vertices[2].y = f32::NAN;
+        assert_eq!(model.validate(), Err(MeshError::NonFiniteVertex { vertex: 2 }));
+        model.vertices[2].y = f32::INFINITY;
+        assert_eq!(model.validate(), Err(MeshError::NonFiniteVertex { vertex: 2 }));
+    }
+
+    #[test]
+    fn index_out_of_bounds() {
+        let mut model = quad();
+        model.indexes[1].z = 4;
+        assert_eq!(model.validate(), Err(MeshError::IndexOutOfBounds { triangle: 1, index: 4 }));
+    }
+
+    #[test]
+    fn degenerate_triangle() {
+        let mut model = quad();
+        model.indexes[1] = Point4::new(0, 2, 2, 0);
+        assert_eq!(model.validate(), Err(MeshError::DegenerateTriangle { triangle: 1 }));
+        // collinear vertices
+        model.vertices.push(Point4::new(2.0, 0.0, 0.0, 0.0));
+        model.indexes[1] = Point4::new(0, 1, 4, 0);
+        assert_eq!(model.validate(), Err(MeshError::DegenerateTriangle { triangle: 1 }));
+    }
+
+    #[test]
+    fn repair_then_validate() {
+        let mut model = quad()
+            .with_normals(vec![Vector3::z(); 5])
+            .with_uvs(vec![Point2::new(0.0, 0.0), Point2::new(1.0, 0.0)]);
+        // unreferenced non-finite vertex, a triangle through it, a missing vertex and a line
+        model.vertices.insert(1, Point4::new(f32::NAN, 0.0, 0.0, 0.0));
+        model.indexes = vec![
+            Point4::new(0, 2, 3, 7),
+            Point4::new(0, 1, 2, 0),
+            Point4::new(0, 3, 9, 0),
+            Point4::new(0, 3, 4, 0),
+            Point4::new(0, 2, 2, 0),
+        ];
+
+        assert_eq!(model.repair(), Ok(3));
+        assert_eq!(model.validate(), Ok(()));
+        assert_eq!(model.vertices, quad().vertices);
+        assert_eq!(model.indexes, vec![Point4::new(0, 1, 2, 7), Point4::new(0, 2, 3, 0)]);
+        assert_eq!(model.normals.len(), 4);
+        assert_eq!(model.uvs, vec![Point2::new(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn repair_drops_unreferenced_vertices() {
+        let mut model = quad();
+        model.indexes.pop();
+        assert_eq!(model.repair(), Ok(0));
+        assert_eq!(model.vertices.len(), 3);
+        assert_eq!(model.validate(), Ok(()));
+    }
+
+    #[test]
+    fn decomposed_transform_composes_back() {
+        let transforms = [
+            Matrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0))
+                * UnitQuaternion::from_euler_angles(0.3, -1.2, 2.5).to_homogeneous()
+                * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 0.5, 1.0)),
+            // shear and reflection
+            Matrix4::new(
+                1.0, 0.5, 0.0, 0.0, 0.0, -1.0, 0.0, 4.0, 0.2, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ),
+        ];
+        for transform in transforms.iter() {
+            let decomposed = Decomposed::new(transform);
+            let linear = decomposed.rotation.to_rotation_matrix().matrix() * decomposed.stretch;
+            assert!((linear - linear_part(transform)).amax() < 1e-5);
+            assert!((decomposed.stretch - decomposed.stretch.transpose()).amax() < 1e-5);
+            assert_eq!(decomposed.translation, transform.fixed_slice::<U3, U1>(0, 3));
+        }
+    }
+
+    #[test]
+    fn primitive_areas_under_non_uniform_scale() {
+        let scaled = |primitive: Primitive, scaling: Vector3<f32>| {
+            Model { scaling, ..Model::from_primitive(primitive) }.surface_area()
+        };
+        let half_extents = Vector3::repeat(1.0);
+        let area = scaled(Primitive::Box { half_extents }, Vector3::new(2.0, 1.0, 3.0));
+        assert!((area - 88.0).abs() < 1e-4);
+        let area = scaled(Primitive::Disk { radius: 1.0 }, Vector3::new(2.0, 5.0, 3.0));
+        assert!((area - 6.0 * PI).abs() < 1e-4);
+        let area = scaled(Primitive::Sphere { radius: 1.0 }, Vector3::repeat(2.0));
+        assert!((area - 16.0 * PI).abs() < 1e-4);
+        let cylinder = Primitive::Cylinder { radius: 1.0, half_height: 1.0 };
+        let area = scaled(cylinder, Vector3::new(2.0, 1.0, 2.0));
+        assert!((area - 16.0 * PI).abs() < 1e-4);
+        assert!(scaled(Primitive::Plane, Vector3::repeat(1.0)).is_infinite());
+    }
+
+    #[test]
+    fn repair_reports_empty() {
+        let mut model = quad();
+        model.indexes = vec![Point4::new(0, 1, 1, 0), Point4::new(0, 1, 8, 0)];
+        assert_eq!(model.repair(), Err(MeshError::Empty));
+        assert!(model.vertices.is_empty() && model.indexes.is_empty());
+    }
+}
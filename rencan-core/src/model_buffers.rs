@@ -1,13 +1,24 @@
 use crate::{
-    hitbox::HitBoxRectangleUniformStd140, light::PointLightUniform, model::ModelUniformInfo, Scene,
+    background::{BackgroundUniformStd140, EquirectangularImage},
+    emission::{scene_emitters, EmitterUniform, EmitterUniformStd140},
+    hitbox::{HitBoxRectangleUniform, HitBoxRectangleUniformStd140},
+    light::{DirectionLightUniform, IesProfile, LightInfo, PointLightUniform, SpotLightUniform},
+    medium::{MediumUniform, MediumUniformStd140},
+    model::ModelUniformInfo,
+    primitive::{PrimitiveUniform, PrimitiveUniformStd140},
+    Scene,
 };
 use crevice::std140::AsStd140;
 use nalgebra::Point4;
-use std::sync::Arc;
+use std::{cell::RefCell, sync::Arc};
 use vulkano::{
     buffer::{
         cpu_pool::{CpuBufferPoolChunk, CpuBufferPoolSubbuffer},
-        BufferUsage, CpuBufferPool, TypedBufferAccess,
+        BufferUsage, CpuAccessibleBuffer, CpuBufferPool, TypedBufferAccess,
+    },
+    descriptor::{
+        descriptor_set::{PersistentDescriptorSet, UnsafeDescriptorSetLayout},
+        DescriptorSet,
     },
     device::Device,
     memory::pool::StdMemoryPool,
@@ -20,9 +31,27 @@ pub struct SceneBuffersStorage {
     pub model_infos: CpuBufferPool<ModelUniformInfoStd140>,
     pub vertices: CpuBufferPool<Point4<f32>>,
     pub indices: CpuBufferPool<Point4<u32>>,
+    pub normals: CpuBufferPool<Point4<f32>>,
+    pub uvs: CpuBufferPool<[f32; 2]>,
     pub hit_boxes: CpuBufferPool<HitBoxRectangleUniformStd140>,
+    pub primitives: CpuBufferPool<PrimitiveUniformStd140>,
+    pub direction_lights: CpuBufferPool<DirectionLightUniform>,
     pub point_lights: CpuBufferPool<PointLightUniform>,
     pub point_lights_count: CpuBufferPool<u32>,
+    pub background: CpuBufferPool<BackgroundUniformStd140>,
+    pub emitters: CpuBufferPool<EmitterUniformStd140>,
+    pub spot_lights: CpuBufferPool<SpotLightUniform>,
+    pub ies_profiles: CpuBufferPool<f32>,
+    pub ambient_light: CpuBufferPool<[f32; 4]>,
+    pub medium: CpuBufferPool<MediumUniformStd140>,
+    background_pixels: RefCell<Option<BackgroundPixels>>,
+    device: Arc<Device>,
+}
+
+/// Pixels of the background image are uploaded once and reused while the image is the same.
+struct BackgroundPixels {
+    image: Option<Arc<EquirectangularImage>>,
+    buffer: Arc<CpuAccessibleBuffer<[[f32; 4]]>>,
 }
 
 impl SceneBuffersStorage {
@@ -41,10 +70,26 @@ impl SceneBuffersStorage {
                 device.clone(),
                 BufferUsage { storage_buffer: true, ..BufferUsage::none() },
             ),
+            normals: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
+            uvs: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
             hit_boxes: CpuBufferPool::new(
                 device.clone(),
                 BufferUsage { storage_buffer: true, ..BufferUsage::none() },
             ),
+            primitives: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
+            direction_lights: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
             point_lights: CpuBufferPool::new(
                 device.clone(),
                 BufferUsage { storage_buffer: true, ..BufferUsage::none() },
@@ -53,60 +98,243 @@ impl SceneBuffersStorage {
                 device.clone(),
                 BufferUsage { uniform_buffer: true, ..BufferUsage::none() },
             ),
+            background: CpuBufferPool::new(device.clone(), BufferUsage::uniform_buffer()),
+            emitters: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
+            spot_lights: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
+            ies_profiles: CpuBufferPool::new(
+                device.clone(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            ),
+            ambient_light: CpuBufferPool::new(device.clone(), BufferUsage::uniform_buffer()),
+            medium: CpuBufferPool::new(device.clone(), BufferUsage::uniform_buffer()),
+            background_pixels: RefCell::new(None),
+            device,
         }
     }
 
+    fn background_pixels(
+        &self,
+        image: Option<&Arc<EquirectangularImage>>,
+    ) -> Arc<CpuAccessibleBuffer<[[f32; 4]]>> {
+        let mut cached = self.background_pixels.borrow_mut();
+        match (&*cached, image) {
+            (Some(BackgroundPixels { image: Some(cached_image), buffer }), Some(image))
+                if Arc::ptr_eq(cached_image, image) =>
+            {
+                return buffer.clone()
+            }
+            (Some(BackgroundPixels { image: None, buffer }), None) => return buffer.clone(),
+            _ => {}
+        }
+
+        // The buffer can not be empty, so backgrounds without an image get one black pixel.
+        let pixels = image.map(|i| i.pixels().to_vec()).unwrap_or_else(|| vec![[0.0; 4]]);
+        let buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            false,
+            pixels.into_iter(),
+        )
+        .unwrap();
+        *cached = Some(BackgroundPixels { image: image.cloned(), buffer: buffer.clone() });
+        buffer
+    }
+
+    fn spot_lights_buffers(
+        &self,
+        scene: &Scene,
+    ) -> (
+        CpuBufferPoolSubbuffer<u32, Arc<StdMemoryPool>>,
+        CpuBufferPoolChunk<SpotLightUniform, Arc<StdMemoryPool>>,
+        CpuBufferPoolChunk<f32, Arc<StdMemoryPool>>,
+    ) {
+        let mut profiles: Vec<&Arc<IesProfile>> = vec![];
+        let mut lights = scene
+            .spot_lights
+            .iter()
+            .map(|light| {
+                let profile_index = match &light.profile {
+                    Some(profile) => match profiles.iter().position(|p| Arc::ptr_eq(p, profile)) {
+                        Some(i) => i as u32,
+                        None => {
+                            profiles.push(profile);
+                            profiles.len() as u32 - 1
+                        }
+                    },
+                    None => u32::MAX,
+                };
+                light.clone().into_uniform(profile_index)
+            })
+            .collect::<Vec<_>>();
+
+        let count = self.counts_u32.next(lights.len() as u32).unwrap();
+        // Buffers can not be empty, shaders read nothing while the count is zero.
+        if lights.is_empty() {
+            lights.push(SpotLightUniform::none());
+        }
+        let mut tables = profiles.iter().flat_map(|p| p.table()).collect::<Vec<_>>();
+        if tables.is_empty() {
+            tables.push(0.0);
+        }
+
+        (count, self.spot_lights.chunk(lights).unwrap(), self.ies_profiles.chunk(tables).unwrap())
+    }
+
     pub fn get_buffers(&self, scene: &Scene) -> SceneBuffers {
-        let models = &scene.models;
+        let models = scene.visible_models().collect::<Vec<_>>();
         let point_lights = &scene.point_lights;
 
         let count = self.counts_u32.next(models.len() as u32).unwrap();
-        let infos = self
-            .model_infos
-            .chunk(
-                models
-                    .iter()
-                    .enumerate()
-                    .map(|(i, m)| m.model().get_uniform_info(i as u32).as_std140()),
-            )
-            .unwrap();
-        let vertices = self
-            .vertices
-            .chunk(
-                models
+        let mut primitives = vec![];
+        let mut infos = models
+            .iter()
+            .map(|(handle, m, parent)| {
+                let primitive_idx = primitives.len() as u32;
+                if let Some(primitive) = &m.model().primitive {
+                    primitives.push(primitive.clone().into_uniform().as_std140());
+                }
+                m.model().get_uniform_info(*handle, primitive_idx, parent).as_std140()
+            })
+            .collect::<Vec<_>>();
+        // Buffers can not be empty, e.g. in scenes without primitives, without meshes or with
+        // all models hidden.
+        if infos.is_empty() {
+            infos.push(ModelUniformInfo::none().as_std140());
+        }
+        let infos = self.model_infos.chunk(infos).unwrap();
+        if primitives.is_empty() {
+            primitives.push(PrimitiveUniform::none().as_std140());
+        }
+        let primitives = self.primitives.chunk(primitives).unwrap();
+        let mut vertices = models
+            .iter()
+            .map(|(_, m, _)| m.model().vertices.iter().cloned())
+            .flatten()
+            .collect::<Vec<_>>();
+        if vertices.is_empty() {
+            vertices.push(Point4::origin());
+        }
+        let vertices = self.vertices.chunk(vertices).unwrap();
+        // One normal and one uv for every vertex, so that they share the offsets of the
+        // vertices. Missing ones are zeros, shaders treat zero normals as flat shading.
+        let mut normals = models
+            .iter()
+            .map(|(_, m, _)| {
+                let model = m.model();
+                model
+                    .normals
                     .iter()
-                    .map(|m| m.model().vertices.iter().cloned())
-                    .flatten()
-                    .collect::<Vec<_>>()
-                    .into_iter(),
-            )
-            .unwrap();
-        let indices = self
-            .indices
-            .chunk(
-                models
+                    .map(|n| Point4::new(n.x, n.y, n.z, 0.0))
+                    .chain(std::iter::repeat(Point4::origin()))
+                    .take(model.vertices.len())
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        if normals.is_empty() {
+            normals.push(Point4::origin());
+        }
+        let normals = self.normals.chunk(normals).unwrap();
+        let mut uvs = models
+            .iter()
+            .map(|(_, m, _)| {
+                let model = m.model();
+                model
+                    .uvs
                     .iter()
-                    .map(|m| m.model().indexes.iter().cloned())
-                    .flatten()
-                    .collect::<Vec<_>>()
-                    .into_iter(),
-            )
-            .unwrap();
-        let hit_boxes = self
-            .hit_boxes
-            .chunk(models.iter().map(|m| m.hit_box().clone().into_uniform().as_std140()))
-            .unwrap();
-        let point_lights =
-            self.point_lights.chunk(point_lights.iter().map(|l| l.clone().into_uniform())).unwrap();
+                    .map(|uv| [uv.x, uv.y])
+                    .chain(std::iter::repeat([0.0; 2]))
+                    .take(model.vertices.len())
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        if uvs.is_empty() {
+            uvs.push([0.0; 2]);
+        }
+        let uvs = self.uvs.chunk(uvs).unwrap();
+        let mut indices = models
+            .iter()
+            .map(|(_, m, _)| m.model().indexes.iter().cloned())
+            .flatten()
+            .collect::<Vec<_>>();
+        if indices.is_empty() {
+            indices.push(Point4::origin());
+        }
+        let indices = self.indices.chunk(indices).unwrap();
+        let mut hit_boxes = models
+            .iter()
+            .map(|(_, m, _)| m.hit_box().clone().into_uniform().as_std140())
+            .collect::<Vec<_>>();
+        if hit_boxes.is_empty() {
+            hit_boxes.push(HitBoxRectangleUniform::none().as_std140());
+        }
+        let hit_boxes = self.hit_boxes.chunk(hit_boxes).unwrap();
+        let direction_lights_count =
+            self.counts_u32.next(scene.direction_lights.len() as u32).unwrap();
+        let direction_lights = if scene.direction_lights.is_empty() {
+            self.direction_lights.chunk(std::iter::once(DirectionLightUniform::none())).unwrap()
+        } else {
+            self.direction_lights
+                .chunk(scene.direction_lights.iter().map(|l| l.clone().into_uniform()))
+                .unwrap()
+        };
         let point_lights_count = self.point_lights_count.next(point_lights.len() as u32).unwrap();
+        let point_lights = if point_lights.is_empty() {
+            self.point_lights.chunk(std::iter::once(PointLightUniform::none())).unwrap()
+        } else {
+            self.point_lights.chunk(point_lights.iter().map(|l| l.clone().into_uniform())).unwrap()
+        };
+        let background = self
+            .background
+            .next(scene.background.clone().into_uniform(scene.image_based_lighting).as_std140())
+            .unwrap();
+        let background_pixels = self.background_pixels(scene.background.image());
+        let mut emitters = scene_emitters(models.iter().map(|(_, m, parent)| (*m, *parent)));
+        let emitters_count = self.counts_u32.next(emitters.len() as u32).unwrap();
+        if emitters.is_empty() {
+            emitters.push(EmitterUniform::none());
+        }
+        let emitters = self.emitters.chunk(emitters.iter().map(|e| e.as_std140())).unwrap();
+        let (spot_lights_count, spot_lights, ies_profiles) = self.spot_lights_buffers(scene);
+        let ambient_light = match &scene.ambient_light {
+            Some(LightInfo { color, intensity }) => {
+                [color.x * intensity, color.y * intensity, color.z * intensity, 0.0]
+            }
+            None => [0.0; 4],
+        };
+        let ambient_light = self.ambient_light.next(ambient_light).unwrap();
+        let medium = match &scene.medium {
+            Some(medium) => medium.clone().into_uniform(),
+            None => MediumUniform::none(),
+        };
+        let medium = self.medium.next(medium.as_std140()).unwrap();
         SceneBuffers {
             count,
             infos,
             vertices,
             indices,
             hit_boxes,
+            primitives,
+            normals,
+            uvs,
+            direction_lights_count,
+            direction_lights,
             point_lights_count,
             point_lights,
+            background,
+            background_pixels,
+            emitters_count,
+            emitters,
+            spot_lights_count,
+            spot_lights,
+            ies_profiles,
+            ambient_light,
+            medium,
         }
     }
 }
@@ -118,6 +346,50 @@ pub struct SceneBuffers {
     pub vertices: CpuBufferPoolChunk<Point4<f32>, Arc<StdMemoryPool>>,
     pub indices: CpuBufferPoolChunk<Point4<u32>, Arc<StdMemoryPool>>,
     pub hit_boxes: CpuBufferPoolChunk<HitBoxRectangleUniformStd140, Arc<StdMemoryPool>>,
+    pub primitives: CpuBufferPoolChunk<PrimitiveUniformStd140, Arc<StdMemoryPool>>,
+    pub normals: CpuBufferPoolChunk<Point4<f32>, Arc<StdMemoryPool>>,
+    pub uvs: CpuBufferPoolChunk<[f32; 2], Arc<StdMemoryPool>>,
+    pub direction_lights_count: CpuBufferPoolSubbuffer<u32, Arc<StdMemoryPool>>,
+    pub direction_lights: CpuBufferPoolChunk<DirectionLightUniform, Arc<StdMemoryPool>>,
     pub point_lights_count: CpuBufferPoolSubbuffer<u32, Arc<StdMemoryPool>>,
     pub point_lights: CpuBufferPoolChunk<PointLightUniform, Arc<StdMemoryPool>>,
+    pub background: CpuBufferPoolSubbuffer<BackgroundUniformStd140, Arc<StdMemoryPool>>,
+    pub background_pixels: Arc<CpuAccessibleBuffer<[[f32; 4]]>>,
+    pub emitters_count: CpuBufferPoolSubbuffer<u32, Arc<StdMemoryPool>>,
+    pub emitters: CpuBufferPoolChunk<EmitterUniformStd140, Arc<StdMemoryPool>>,
+    pub spot_lights_count: CpuBufferPoolSubbuffer<u32, Arc<StdMemoryPool>>,
+    pub spot_lights: CpuBufferPoolChunk<SpotLightUniform, Arc<StdMemoryPool>>,
+    pub ies_profiles: CpuBufferPoolChunk<f32, Arc<StdMemoryPool>>,
+    pub ambient_light: CpuBufferPoolSubbuffer<[f32; 4], Arc<StdMemoryPool>>,
+    pub medium: CpuBufferPoolSubbuffer<MediumUniformStd140, Arc<StdMemoryPool>>,
+}
+
+impl SceneBuffers {
+    /// Makes the descriptor set with models of the scene, `set = 1` in shaders.
+    pub fn models_set(
+        &self,
+        layout: Arc<UnsafeDescriptorSetLayout>,
+    ) -> Arc<dyn DescriptorSet + Send + Sync> {
+        Arc::new(
+            PersistentDescriptorSet::start(layout)
+                .add_buffer(self.count.clone())
+                .unwrap()
+                .add_buffer(self.infos.clone())
+                .unwrap()
+                .add_buffer(self.vertices.clone())
+                .unwrap()
+                .add_buffer(self.indices.clone())
+                .unwrap()
+                .add_buffer(self.hit_boxes.clone())
+                .unwrap()
+                .add_buffer(self.primitives.clone())
+                .unwrap()
+                .add_buffer(self.normals.clone())
+                .unwrap()
+                .add_buffer(self.uvs.clone())
+                .unwrap()
+                .build()
+                .unwrap(),
+        )
+    }
 }
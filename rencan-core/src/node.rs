@@ -0,0 +1,44 @@
+use nalgebra::Isometry3;
+
+use crate::{handle::Handle, model::ModelHandle};
+
+pub type NodeHandle = Handle<Node>;
+
+/// Group of models and child nodes in a [`Scene`](crate::Scene) which are moved and hidden
+/// together. The transform of a model is relative to its node, the transform of a node is
+/// relative to its parent.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub transform: Isometry3<f32>,
+    /// Hidden nodes are not uploaded with all their descendants.
+    pub visible: bool,
+    parent: Option<NodeHandle>,
+    children: Vec<NodeHandle>,
+    models: Vec<ModelHandle>,
+}
+
+impl Node {
+    pub(crate) fn new(parent: Option<NodeHandle>, transform: Isometry3<f32>) -> Self {
+        Node { transform, visible: true, parent, children: vec![], models: vec![] }
+    }
+
+    pub fn parent(&self) -> Option<NodeHandle> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeHandle] {
+        &self.children
+    }
+
+    pub fn models(&self) -> &[ModelHandle] {
+        &self.models
+    }
+
+    pub(crate) fn children_mut(&mut self) -> &mut Vec<NodeHandle> {
+        &mut self.children
+    }
+
+    pub(crate) fn models_mut(&mut self) -> &mut Vec<ModelHandle> {
+        &mut self.models
+    }
+}
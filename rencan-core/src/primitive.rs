@@ -0,0 +1,120 @@
+use crevice::std140::AsStd140;
+use nalgebra::{Matrix3, Point3, Vector3};
+use std::f32::consts::PI;
+
+/// Surface intersected analytically instead of by triangles. Primitives are defined in the
+/// model space, [`Model`](crate::Model) places them into the scene like meshes.
+#[derive(Debug, Clone)]
+pub enum Primitive {
+    Sphere { radius: f32 },
+    /// Infinite plane `y = 0`.
+    Plane,
+    Box { half_extents: Vector3<f32> },
+    /// Disk in the plane `y = 0`.
+    Disk { radius: f32 },
+    /// Capped cylinder along the `y` axis.
+    Cylinder { radius: f32, half_height: f32 },
+}
+
+impl Primitive {
+    /// Bounds in the model space, `None` for unbounded primitives.
+    pub fn bounds(&self) -> Option<(Point3<f32>, Point3<f32>)> {
+        let half = match self {
+            Primitive::Sphere { radius } => Vector3::repeat(*radius),
+            Primitive::Plane => return None,
+            Primitive::Box { half_extents } => *half_extents,
+            Primitive::Disk { radius } => Vector3::new(*radius, 0.0, *radius),
+            Primitive::Cylinder { radius, half_height } => {
+                Vector3::new(*radius, *half_height, *radius)
+            }
+        };
+        Some((Point3::from(-half), Point3::from(half)))
+    }
+
+    /// Area after the linear part `linear` of the model transform, infinite for unbounded
+    /// primitives. Boxes and disks are exact, spheres and the sides of cylinders under
+    /// non-uniform scale are approximated within about one percent.
+    pub fn surface_area(&self, linear: &Matrix3<f32>) -> f32 {
+        let axis = |i: usize| linear.column(i).into_owned();
+        // area of the image of the unit square spanned by two axes
+        let face = |i: usize, j: usize| axis(i).cross(&axis(j)).norm();
+        match self {
+            Primitive::Sphere { radius } => {
+                let axes = linear.singular_values() * *radius;
+                ellipsoid_area(axes.x, axes.y, axes.z)
+            }
+            Primitive::Plane => f32::INFINITY,
+            Primitive::Box { half_extents: h } => {
+                8.0 * (h.y * h.z * face(1, 2) + h.z * h.x * face(2, 0) + h.x * h.y * face(0, 1))
+            }
+            Primitive::Disk { radius } => PI * radius * radius * face(0, 2),
+            Primitive::Cylinder { radius, half_height } => {
+                let caps = 2.0 * PI * radius * radius * face(0, 2);
+                let height = axis(1);
+                let direction = match height.try_normalize(0.0) {
+                    Some(direction) => direction,
+                    None => return caps,
+                };
+                // the side is a prism over the section perpendicular to the axis
+                let section = |v: Vector3<f32>| (v - direction * v.dot(&direction)) * *radius;
+                let (a, b) = ellipse_axes(&section(axis(0)), &section(axis(2)));
+                caps + 2.0 * half_height * height.norm() * ellipse_perimeter(a, b)
+            }
+        }
+    }
+
+    pub fn into_uniform(self) -> PrimitiveUniform {
+        let (kind, size) = match self {
+            Primitive::Sphere { radius } => (0, Vector3::new(radius, 0.0, 0.0)),
+            Primitive::Plane => (1, Vector3::zeros()),
+            Primitive::Box { half_extents } => (2, half_extents),
+            Primitive::Disk { radius } => (3, Vector3::new(radius, 0.0, 0.0)),
+            Primitive::Cylinder { radius, half_height } => {
+                (4, Vector3::new(radius, half_height, 0.0))
+            }
+        };
+        PrimitiveUniform { size: size.into(), kind }
+    }
+}
+
+/// Knud Thomsen's approximation of the area of an ellipsoid with the semi-axes `a`, `b`, `c`.
+fn ellipsoid_area(a: f32, b: f32, c: f32) -> f32 {
+    const P: f32 = 1.6075;
+    let (a, b, c) = (a.powf(P), b.powf(P), c.powf(P));
+    4.0 * PI * ((a * b + a * c + b * c) / 3.0).powf(1.0 / P)
+}
+
+/// Semi-axes of the ellipse with the conjugate semi-diameters `u` and `v`.
+fn ellipse_axes(u: &Vector3<f32>, v: &Vector3<f32>) -> (f32, f32) {
+    let (uu, vv, uv) = (u.norm_squared(), v.norm_squared(), u.dot(v));
+    let half_trace = (uu + vv) / 2.0;
+    let d = (half_trace * half_trace - (uu * vv - uv * uv)).max(0.0).sqrt();
+    ((half_trace + d).sqrt(), (half_trace - d).max(0.0).sqrt())
+}
+
+/// Ramanujan's approximation of the perimeter of an ellipse with the semi-axes `a` and `b`.
+fn ellipse_perimeter(a: f32, b: f32) -> f32 {
+    if a + b <= 0.0 {
+        return 0.0;
+    }
+    let h = ((a - b) / (a + b)).powi(2);
+    PI * (a + b) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()))
+}
+
+/// Value of `ModelInfo::primitive_idx` for triangle meshes.
+pub const NO_PRIMITIVE: u32 = u32::MAX;
+
+#[derive(AsStd140)]
+pub struct PrimitiveUniform {
+    size: mint::Vector3<f32>,
+    kind: u32,
+}
+
+impl PrimitiveUniform {
+    /// Placeholder for the buffer of a scene without primitives, which can not be empty.
+    pub fn none() -> Self {
+        PrimitiveUniform { size: mint::Vector3 { x: 0.0, y: 0.0, z: 0.0 }, kind: 0 }
+    }
+}
+
+pub type PrimitiveUniformStd140 = <PrimitiveUniform as AsStd140>::Std140Type;
@@ -1,9 +1,78 @@
-use nalgebra::Point4;
+use nalgebra::{Point3, Point4, Vector3};
 
-#[repr(C, align(64))]
+// Layout matches std140 array of `Ray` in shaders
+#[repr(C, align(16))]
 #[derive(Debug, Clone)]
 pub struct Ray {
     pub origin: Point4<f32>,
     pub direction: Point4<f32>,
     pub max_distance: f32,
+    /// Moment in the shutter interval, from 0 at the opening to 1 at the closing.
+    pub time: f32,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Self {
+        let direction = direction.normalize();
+        Ray {
+            origin: Point4::new(origin.x, origin.y, origin.z, 0.0),
+            direction: Point4::new(direction.x, direction.y, direction.z, 0.0),
+            max_distance,
+            time: 0.0,
+        }
+    }
+    pub fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
+    }
+    /// Origin for a ray leaving the surface at the point, e.g. a hit from
+    /// [`App::cast_rays`](crate::app::App::cast_rays), which does not hit the surface again. The
+    /// normal must face the side of the new ray. Matches `offset_ray` in shaders.
+    pub fn offset_origin(point: Point3<f32>, normal: &Vector3<f32>) -> Point3<f32> {
+        const ORIGIN: f32 = 1.0 / 32.0;
+        const FLOAT_SCALE: f32 = 1.0 / 65536.0;
+        const INT_SCALE: f32 = 256.0;
+
+        let offset = |p: f32, n: f32| {
+            if p.abs() < ORIGIN {
+                return p + FLOAT_SCALE * n;
+            }
+            // moves the point by a count of ulps, which scale with its magnitude
+            let ulps = (INT_SCALE * n) as i32;
+            let ulps = if p < 0.0 { -ulps } else { ulps };
+            f32::from_bits((p.to_bits() as i32).wrapping_add(ulps) as u32)
+        };
+        Point3::new(offset(point.x, normal.x), offset(point.y, normal.y), offset(point.z, normal.z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_near_origin_is_fixed() {
+        let normal = Vector3::new(1.0, -1.0, 0.0);
+        let offset = Ray::offset_origin(Point3::new(0.01, 0.0, -0.02), &normal);
+        assert_eq!(offset, Point3::new(0.01 + 1.0 / 65536.0, -1.0 / 65536.0, -0.02));
+    }
+
+    #[test]
+    fn offset_follows_the_normal_for_negative_coordinates() {
+        let point = Point3::new(-10.0, -10.0, -10.0);
+        let offset = Ray::offset_origin(point, &Vector3::new(1.0, -1.0, 0.0));
+        assert!(offset.x > point.x);
+        assert!(offset.y < point.y);
+        assert_eq!(offset.z, point.z);
+    }
+
+    #[test]
+    fn offset_moves_to_the_normal_side_at_large_magnitudes() {
+        let normal = Vector3::new(1.0, 1.0, -1.0).normalize();
+        for &point in [Point3::new(1e5, -1e5, 1e5), Point3::new(-1e5, 1e5, -1e5)].iter() {
+            let offset = Ray::offset_origin(point, &normal);
+            let moved = offset - point;
+            assert!(moved.x > 0.0 && moved.y > 0.0 && moved.z < 0.0, "{:?}", moved);
+        }
+    }
 }
@@ -0,0 +1,159 @@
+use std::{cell::RefCell, sync::Arc};
+
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer, CpuBufferPool},
+    command_buffer::{AutoCommandBufferBuilder, CommandBuffer},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout, DescriptorSet,
+        PipelineLayoutAbstract,
+    },
+    device::Device,
+    pipeline::ComputePipeline,
+    sync::GpuFuture,
+};
+
+use crate::{
+    intersection::{Intersection, IntersectionUniform},
+    AppInfo, Ray, Scene,
+};
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "../rencan-render/shaders/ray_cast.glsl"
+    }
+}
+
+/// Must match `local_size_x` in `ray_cast.glsl`.
+const LOCAL_SIZE_X: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayCastMode {
+    /// Finds the closest intersection of every ray.
+    ClosestHit,
+    /// Finds any intersection of every ray. Faster, suitable for line-of-sight queries. Both faces
+    /// of every model are hit regardless of its cull mode.
+    AnyHit,
+}
+
+/// Traces arbitrary rays against the scene with the same kernel which is used for rendering.
+pub struct RayCaster {
+    pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
+    infos: CpuBufferPool<cs::ty::QueryInfo>,
+    /// Models of the scene by its version, uploaded again only when the scene changes.
+    models_set: RefCell<Option<(u64, Arc<dyn DescriptorSet + Send + Sync>)>>,
+}
+
+impl RayCaster {
+    pub fn new(device: Arc<Device>) -> Self {
+        let shader = cs::Shader::load(device.clone()).unwrap();
+        let pipeline = Arc::new(
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
+        );
+        RayCaster {
+            pipeline,
+            infos: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+            models_set: RefCell::new(None),
+        }
+    }
+
+    fn models_set(&self, scene: &Scene) -> Arc<dyn DescriptorSet + Send + Sync> {
+        let mut cached = self.models_set.borrow_mut();
+        match &*cached {
+            Some((version, set)) if *version == scene.version() => set.clone(),
+            _ => {
+                let set = scene
+                    .frame_buffers()
+                    .models_set(self.pipeline.layout().descriptor_set_layout(1).unwrap().clone());
+                *cached = Some((scene.version(), set.clone()));
+                set
+            }
+        }
+    }
+
+    /// Returns intersections in the same order as rays. Blocks until the GPU finishes. The scene
+    /// is uploaded only if its [`version`](Scene::version) changed since the last query.
+    pub fn cast(
+        &self,
+        info: &AppInfo,
+        scene: &Scene,
+        rays: &[Ray],
+        mode: RayCastMode,
+    ) -> Vec<Intersection> {
+        if rays.is_empty() {
+            return vec![];
+        }
+
+        let device = info.device.clone();
+        let rays_count = rays.len() as u32;
+
+        let rays_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+            false,
+            rays.iter().cloned(),
+        )
+        .unwrap();
+        let intersections = unsafe {
+            CpuAccessibleBuffer::<[IntersectionUniform]>::uninitialized_array(
+                device.clone(),
+                rays.len(),
+                BufferUsage { storage_buffer: true, ..BufferUsage::none() },
+                false,
+            )
+            .unwrap()
+        };
+
+        let set_1 = self.models_set(scene);
+
+        let max_workgroups = device.physical_device().limits().max_compute_work_group_count()[0];
+        let rays_per_dispatch = max_workgroups.saturating_mul(LOCAL_SIZE_X);
+
+        let mut command =
+            AutoCommandBufferBuilder::new(device.clone(), info.graphics_queue.family()).unwrap();
+
+        for rays_offset in (0..rays_count).step_by(rays_per_dispatch as usize) {
+            let query_info = cs::ty::QueryInfo {
+                rays_offset,
+                rays_count,
+                any_hit: (mode == RayCastMode::AnyHit) as u32,
+            };
+            let set_0 = Arc::new(
+                PersistentDescriptorSet::start(
+                    self.pipeline.layout().descriptor_set_layout(0).unwrap().clone(),
+                )
+                .add_buffer(self.infos.next(query_info).unwrap())
+                .unwrap()
+                .add_buffer(rays_buffer.clone())
+                .unwrap()
+                .add_buffer(intersections.clone())
+                .unwrap()
+                .build()
+                .unwrap(),
+            );
+
+            let count = rays_per_dispatch.min(rays_count - rays_offset);
+            command
+                .dispatch(
+                    [(count + LOCAL_SIZE_X - 1) / LOCAL_SIZE_X, 1, 1],
+                    self.pipeline.clone(),
+                    (set_0, set_1.clone()),
+                    (),
+                )
+                .unwrap();
+        }
+
+        command
+            .build()
+            .unwrap()
+            .execute(info.graphics_queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let intersections = intersections.read().unwrap();
+        intersections.iter().map(|i| Intersection::from(*i)).collect()
+    }
+}
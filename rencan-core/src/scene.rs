@@ -1,29 +1,307 @@
 use crate::{
-    light::{DirectionLight, PointLight},
-    model::AppModel,
+    animation::Animation,
+    background::Background,
+    handle::HandleMap,
+    light::{DirectionLight, LightInfo, PointLight, SpotLight},
+    medium::Medium,
+    model::{AppModel, ModelHandle},
     model_buffers::{SceneBuffers, SceneBuffersStorage},
+    node::{Node, NodeHandle},
+};
+use nalgebra::Isometry3;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
 };
-use std::sync::Arc;
 use vulkano::device::Device;
 
 pub struct Scene {
-    pub models: Vec<AppModel>,
-    pub global_light: DirectionLight,
+    models: HandleMap<AppModel>,
+    nodes: HandleMap<Node>,
+    version: u64,
+    pub direction_lights: Vec<DirectionLight>,
     pub buffers: SceneBuffersStorage,
     pub point_lights: Vec<PointLight>,
+    pub spot_lights: Vec<SpotLight>,
+    /// Constant light from all directions, attenuated by the ambient occlusion pass if there
+    /// is one.
+    pub ambient_light: Option<LightInfo>,
+    pub medium: Option<Medium>,
+    pub background: Background,
+    /// Lights diffuse surfaces by the background in the direction of their normal.
+    pub image_based_lighting: bool,
+    pub animation: Animation,
 }
 
 impl Scene {
     pub fn new(
         device: Arc<Device>,
         models: Vec<AppModel>,
-        global_light: DirectionLight,
+        direction_lights: Vec<DirectionLight>,
         point_lights: Vec<PointLight>,
     ) -> Self {
-        Scene { models, global_light, buffers: SceneBuffersStorage::init(device), point_lights }
+        let mut handles = HandleMap::new();
+        models.into_iter().for_each(|model| {
+            handles.insert(model);
+        });
+        Scene {
+            models: handles,
+            nodes: HandleMap::new(),
+            version: next_version(),
+            direction_lights,
+            buffers: SceneBuffersStorage::init(device),
+            point_lights,
+            spot_lights: vec![],
+            ambient_light: None,
+            medium: None,
+            background: Background::default(),
+            image_based_lighting: false,
+            animation: Animation::new(),
+        }
+    }
+
+    pub fn with_spot_lights(mut self, spot_lights: Vec<SpotLight>) -> Self {
+        self.spot_lights = spot_lights;
+        self
+    }
+
+    pub fn with_ambient_light(mut self, ambient_light: LightInfo) -> Self {
+        self.ambient_light = Some(ambient_light);
+        self
+    }
+
+    pub fn with_medium(mut self, medium: Medium) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_image_based_lighting(mut self) -> Self {
+        self.image_based_lighting = true;
+        self
+    }
+
+    pub fn with_animation(mut self, animation: Animation) -> Self {
+        self.animation = animation;
+        self
+    }
+
+    /// Sets animated models, nodes and lights to their state at the time in seconds. The camera
+    /// is not a part of the scene, see [`Animation::evaluate_camera`].
+    pub fn evaluate(&mut self, time: f32) {
+        let animation = std::mem::take(&mut self.animation);
+        animation.apply(self, time);
+        self.animation = animation;
+    }
+
+    /// Changes whenever the models or the nodes may have changed, i.e. on every mutable access
+    /// to them. Versions are unique across all scenes, so passes can skip the work which
+    /// depends only on the geometry while the version stays the same.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn touch(&mut self) {
+        self.version = next_version();
+    }
+
+    /// Adds the model to the scene. The handle stays valid until the model is removed.
+    pub fn insert(&mut self, mut model: AppModel) -> ModelHandle {
+        self.touch();
+        model.node = None;
+        self.models.insert(model)
+    }
+
+    /// Adds the model to the node, the transform of the model becomes relative to the node.
+    pub fn insert_into(&mut self, node: NodeHandle, model: AppModel) -> ModelHandle {
+        let handle = self.insert(model);
+        self.attach(handle, Some(node));
+        handle
+    }
+
+    /// Returns `None` if the model was already removed.
+    pub fn remove(&mut self, handle: ModelHandle) -> Option<AppModel> {
+        self.touch();
+        self.attach(handle, None);
+        self.models.remove(handle)
+    }
+
+    pub fn get(&self, handle: ModelHandle) -> Option<&AppModel> {
+        self.models.get(handle)
+    }
+
+    pub fn get_mut(&mut self, handle: ModelHandle) -> Option<&mut AppModel> {
+        self.touch();
+        self.models.get_mut(handle)
+    }
+
+    /// Models in the order of upload to the GPU.
+    pub fn models(&self) -> impl Iterator<Item = (ModelHandle, &AppModel)> {
+        self.models.iter()
+    }
+
+    pub fn models_mut(&mut self) -> impl Iterator<Item = (ModelHandle, &mut AppModel)> {
+        self.touch();
+        self.models.iter_mut()
+    }
+
+    /// Moves the model into the node, `None` makes the transform of the model global.
+    pub fn attach(&mut self, model: ModelHandle, node: Option<NodeHandle>) {
+        self.touch();
+        let node = node.filter(|node| self.nodes.contains(*node));
+        let previous = match self.models.get_mut(model) {
+            Some(app_model) => std::mem::replace(&mut app_model.node, node),
+            None => return,
+        };
+        if let Some(previous) = previous.and_then(|previous| self.nodes.get_mut(previous)) {
+            previous.models_mut().retain(|m| *m != model);
+        }
+        if let Some(node) = node.and_then(|node| self.nodes.get_mut(node)) {
+            node.models_mut().push(model);
+        }
+    }
+
+    /// Adds an empty node, a node without a parent is relative to the world.
+    pub fn add_node(
+        &mut self,
+        parent: Option<NodeHandle>,
+        transform: Isometry3<f32>,
+    ) -> NodeHandle {
+        self.touch();
+        let parent = parent.filter(|parent| self.nodes.contains(*parent));
+        let handle = self.nodes.insert(Node::new(parent, transform));
+        if let Some(parent) = parent.and_then(|parent| self.nodes.get_mut(parent)) {
+            parent.children_mut().push(handle);
+        }
+        handle
+    }
+
+    /// Removes the node with all its descendants and their models, which are returned.
+    pub fn remove_node(&mut self, handle: NodeHandle) -> Vec<AppModel> {
+        self.touch();
+        let node = match self.nodes.remove(handle) {
+            Some(node) => node,
+            None => return vec![],
+        };
+        if let Some(parent) = node.parent().and_then(|parent| self.nodes.get_mut(parent)) {
+            parent.children_mut().retain(|child| *child != handle);
+        }
+        let mut removed = node
+            .models()
+            .iter()
+            .filter_map(|model| self.models.remove(*model))
+            .map(|mut model| {
+                model.node = None;
+                model
+            })
+            .collect::<Vec<_>>();
+        for child in node.children() {
+            removed.extend(self.remove_node(*child));
+        }
+        removed
+    }
+
+    pub fn node(&self, handle: NodeHandle) -> Option<&Node> {
+        self.nodes.get(handle)
+    }
+
+    /// The transform and the visibility of the node can be changed directly, they apply to the
+    /// whole subtree.
+    pub fn node_mut(&mut self, handle: NodeHandle) -> Option<&mut Node> {
+        self.touch();
+        self.nodes.get_mut(handle)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeHandle, &Node)> {
+        self.nodes.iter()
+    }
+
+    /// Transform of the node composed with the transforms of all its ancestors.
+    pub fn world_transform(&self, handle: NodeHandle) -> Option<Isometry3<f32>> {
+        let node = self.nodes.get(handle)?;
+        match node.parent() {
+            Some(parent) => Some(self.world_transform(parent)? * node.transform),
+            None => Some(node.transform),
+        }
+    }
+
+    /// Whether the node and all its ancestors are visible.
+    pub fn is_node_visible(&self, handle: NodeHandle) -> bool {
+        match self.nodes.get(handle) {
+            Some(node) => node.visible && node.parent().map_or(true, |p| self.is_node_visible(p)),
+            None => false,
+        }
+    }
+
+    /// Models which are not hidden by their nodes with the world transforms of the nodes, in
+    /// the order of upload to the GPU.
+    pub fn visible_models(
+        &self,
+    ) -> impl Iterator<Item = (ModelHandle, &AppModel, Isometry3<f32>)> + '_ {
+        self.models.iter().filter_map(move |(handle, model)| match model.node() {
+            Some(node) if !self.is_node_visible(node) => None,
+            Some(node) => Some((handle, model, self.world_transform(node)?)),
+            None => Some((handle, model, Isometry3::identity())),
+        })
     }
 
     pub fn frame_buffers(&self) -> SceneBuffers {
         self.buffers.get_buffers(self)
     }
 }
+
+fn next_version() -> u64 {
+    static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{model::Model, primitive::Primitive};
+    use vulkano::{
+        buffer::TypedBufferAccess,
+        device::{DeviceExtensions, Features},
+        instance::{Instance, InstanceExtensions, PhysicalDevice},
+    };
+
+    /// Device of the first physical device, `None` where Vulkan is not available.
+    pub(crate) fn device() -> Option<Arc<Device>> {
+        let instance = Instance::new(None, &InstanceExtensions::none(), None).ok()?;
+        let physical = PhysicalDevice::enumerate(&instance).next()?;
+        let queue_family = physical.queue_families().next()?;
+        let features = Features::none();
+        let extensions = DeviceExtensions::none();
+        let (device, _) =
+            Device::new(physical, &features, &extensions, std::iter::once((queue_family, 0.5)))
+                .ok()?;
+        Some(device)
+    }
+
+    #[test]
+    fn buffers_of_scene_with_everything_hidden() {
+        let device = match device() {
+            Some(device) => device,
+            None => return,
+        };
+        let mut scene = Scene::new(device, vec![], vec![], vec![]);
+        let node = scene.add_node(None, Isometry3::identity());
+        let sphere = AppModel::new(Model::from_primitive(Primitive::Sphere { radius: 1.0 }));
+        scene.insert_into(node, sphere);
+        scene.node_mut(node).unwrap().visible = false;
+        assert_eq!(scene.visible_models().count(), 0);
+
+        // placeholders keep the buffers non-empty
+        let buffers = scene.frame_buffers();
+        assert_eq!(buffers.infos.len(), 1);
+        assert_eq!(buffers.hit_boxes.len(), 1);
+        assert_eq!(buffers.primitives.len(), 1);
+        assert_eq!(buffers.vertices.len(), 1);
+        assert_eq!(buffers.point_lights.len(), 1);
+        assert_eq!(buffers.emitters.len(), 1);
+    }
+}
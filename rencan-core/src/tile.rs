@@ -0,0 +1,34 @@
+use crate::Screen;
+
+/// Size of a workgroup of every screen-space shader. Must match `include/tile.glsl`.
+pub const LOCAL_SIZE: [u32; 2] = [8, 8];
+
+/// Default size of a tile in pixels. Every tile is dispatched in its own command buffer.
+pub const DEFAULT_TILE_SIZE: u32 = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub offset: [u32; 2],
+    pub size: [u32; 2],
+}
+
+impl Tile {
+    pub fn workgroups(&self) -> [u32; 3] {
+        [
+            (self.size[0] + LOCAL_SIZE[0] - 1) / LOCAL_SIZE[0],
+            (self.size[1] + LOCAL_SIZE[1] - 1) / LOCAL_SIZE[1],
+            1,
+        ]
+    }
+}
+
+/// Splits the screen into tiles of at most `tile_size` x `tile_size` pixels.
+pub fn tiles(screen: &Screen, tile_size: u32) -> impl Iterator<Item = Tile> {
+    let (width, height) = (screen.width(), screen.height());
+    (0..height).step_by(tile_size as usize).flat_map(move |y| {
+        (0..width).step_by(tile_size as usize).map(move |x| Tile {
+            offset: [x, y],
+            size: [tile_size.min(width - x), tile_size.min(height - y)],
+        })
+    })
+}
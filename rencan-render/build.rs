@@ -3,11 +3,20 @@ use std::error::Error;
 fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rerun-if-changed=shaders/include/defs.glsl");
     println!("cargo:rerun-if-changed=shaders/include/ray_tracing.glsl");
+    println!("cargo:rerun-if-changed=shaders/include/tile.glsl");
+    println!("cargo:rerun-if-changed=shaders/include/background.glsl");
+    println!("cargo:rerun-if-changed=shaders/include/sampling.glsl");
+    println!("cargo:rerun-if-changed=shaders/include/quaternion.glsl");
     println!("cargo:rerun-if-changed=shaders/compute_rays.glsl");
     println!("cargo:rerun-if-changed=shaders/show_xyz_ordinates.glsl");
     println!("cargo:rerun-if-changed=shaders/ray_tracing.glsl");
     println!("cargo:rerun-if-changed=shaders/checkboard_pattern.glsl");
-    println!("cargo:rerun-if-changed=shaders/facing_ratio.glsl");
     println!("cargo:rerun-if-changed=shaders/lightning.glsl");
+    println!("cargo:rerun-if-changed=shaders/denoise.glsl");
+    println!("cargo:rerun-if-changed=shaders/aovs.glsl");
+    println!("cargo:rerun-if-changed=shaders/debug_view.glsl");
+    println!("cargo:rerun-if-changed=shaders/ray_cast.glsl");
+    println!("cargo:rerun-if-changed=shaders/ambient_occlusion.glsl");
+    println!("cargo:rerun-if-changed=shaders/motion_blur.glsl");
     Ok(())
 }
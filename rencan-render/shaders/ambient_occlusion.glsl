@@ -0,0 +1,96 @@
+#version 450
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "include/defs.glsl"
+
+// set0 is global for app
+layout(set = 0, binding = 0) readonly uniform Info {
+    uvec2 screen;
+};
+layout(std140, set = 0, binding = 1) readonly uniform Camera {
+    vec3 pos;
+    mat3 rotation;
+    float fov;
+    vec3 end_pos;
+    vec4 orientation;
+    vec4 end_orientation;
+    // Sub-frame of a motion blurred frame, seeds its own random numbers.
+    uint shutter_sample;
+    uint shutter_samples;
+};
+layout(std140, set = 0, binding = 2) readonly buffer PrimaryRays {
+    Ray primary_rays[];
+};
+layout(std140, set = 0, binding = 3) readonly buffer PrimaryIntersections {
+    Intersection primary_rays_intersections[];
+};
+layout(set = 0, binding = 4, rgba8) writeonly uniform image2D resultImage;
+layout(std430, set = 0, binding = 5) writeonly buffer AmbientOcclusion {
+    float ambient_occlusion[];
+};
+
+// set1 for models
+layout(std140, set = 1, binding = 0) readonly uniform SceneInfo {
+    uint model_counts;
+};
+layout(std140, set = 1, binding = 1) readonly buffer ModelInfos {
+    ModelInfo[] models;
+};
+layout(set = 1, binding = 2) readonly buffer Vertices {
+    vec3[] vertices;
+};
+layout(std140, set = 1, binding = 3) readonly buffer Indexes {
+    uvec3[] indexes;
+};
+layout(std140, set = 1, binding = 4) readonly buffer HitBoxes {
+    HitBoxRectangle[] hit_boxes;
+};
+layout(std140, set = 1, binding = 5) readonly buffer Primitives {
+    Primitive[] primitives;
+};
+layout(set = 1, binding = 6) readonly buffer Normals {
+    vec3[] normals;
+};
+layout(set = 1, binding = 7) readonly buffer Uvs {
+    vec2[] uvs;
+};
+
+// set2 for the occlusion settings
+layout(std140, set = 2, binding = 0) readonly uniform AmbientOcclusionInfo {
+    uint samples;
+    float max_distance;
+};
+
+#include "include/tile.glsl"
+#include "include/quaternion.glsl"
+#include "include/ray_tracing.glsl"
+#include "include/sampling.glsl"
+
+void main() {
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
+
+    Intersection inter = primary_rays_intersections[idx];
+    if (inter.is_intersect == 0) {
+        ambient_occlusion[idx] = 1.0;
+        return;
+    }
+
+    seed_random(idx + shutter_sample * screen.x * screen.y, RANDOM_STREAM_AMBIENT_OCCLUSION);
+    shutter_time = primary_rays[idx].time;
+
+    vec3 point = offset_ray(inter.point, inter.normal);
+    uint visible = 0;
+    for (uint i = 0; i < samples; i++) {
+        vec3 direction = sample_cosine_hemisphere(inter.normal, stratified_sample(i, samples));
+        if (trace_any(Ray(point, vec4(direction, 0.0), max_distance, shutter_time), VISIBLE_SHADOW).is_intersect == 0) {
+            visible++;
+        }
+    }
+
+    ambient_occlusion[idx] = float(visible) / float(samples);
+}
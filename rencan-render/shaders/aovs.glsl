@@ -0,0 +1,103 @@
+#version 450
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "include/defs.glsl"
+
+// set0 is global for app
+layout(set = 0, binding = 0) readonly uniform Info {
+    uvec2 screen;
+};
+layout(std140, set = 0, binding = 1) readonly uniform Camera {
+    vec3 pos;
+    mat3 rotation;
+    float fov;
+};
+layout(std140, set = 0, binding = 2) readonly buffer PrimaryRays {
+    Ray primary_rays[];
+};
+layout(std140, set = 0, binding = 3) readonly buffer PrimaryIntersections {
+    Intersection primary_rays_intersections[];
+};
+
+// set1 for models
+layout(std140, set = 1, binding = 0) readonly uniform SceneInfo {
+    uint model_counts;
+};
+layout(std140, set = 1, binding = 1) readonly buffer ModelInfos {
+    ModelInfo[] models;
+};
+layout(std140, set = 1, binding = 3) readonly buffer Indexes {
+    uvec3[] indexes;
+};
+layout(set = 1, binding = 7) readonly buffer Uvs {
+    vec2[] uvs;
+};
+
+// set2 for the output variable
+layout(std140, set = 2, binding = 0) readonly uniform AovInfo {
+    uint aov;
+};
+layout(set = 2, binding = 1, rgba32f) writeonly uniform image2D aovImage;
+
+#include "include/tile.glsl"
+
+// Must match rencan_core::aov::Aov
+#define AOV_DEPTH 0u
+#define AOV_NORMAL 1u
+#define AOV_ALBEDO 2u
+#define AOV_OBJECT_ID 3u
+#define AOV_BARYCENTRICS 4u
+#define AOV_FACING_RATIO 5u
+#define AOV_POSITION 6u
+#define AOV_UV 7u
+
+vec3 compute_aov(Intersection inter, Ray primary_ray) {
+    switch (aov) {
+        case AOV_DEPTH: {
+            vec3 forward = rotation * vec3(0.0, 0.0, -1.0);
+            return vec3(dot(inter.point - pos, forward));
+        }
+        case AOV_NORMAL:
+            return inter.normal;
+        case AOV_ALBEDO:
+            return vec3(models[inter.model_id].albedo);
+        case AOV_OBJECT_ID:
+            // Index and generation separately, a float can not hold the whole id exactly.
+            return vec3(float(inter.model_handle & 0xFFFFFu), float(inter.model_handle >> 20), 0.0);
+        case AOV_BARYCENTRICS:
+            return vec3(inter.barycentric_coords, 0.0);
+        case AOV_FACING_RATIO:
+            return vec3(max(dot(inter.normal, -primary_ray.direction.xyz), 0.0));
+        case AOV_POSITION:
+            return inter.point;
+        case AOV_UV: {
+            if (models[inter.model_id].primitive_idx != NO_PRIMITIVE) {
+                return vec3(0.0);
+            }
+            uvec3 index = indexes[inter.triangle_idx] + inter.vertices_offset;
+            vec2 coords = inter.barycentric_coords;
+            vec2 uv = (1.0 - coords.x - coords.y) * uvs[index.x] + coords.x * uvs[index.y] +
+                coords.y * uvs[index.z];
+            return vec3(uv, 0.0);
+        }
+    }
+    return vec3(0.0);
+}
+
+void main() {
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
+
+    Intersection inter = primary_rays_intersections[idx];
+
+    if (inter.is_intersect == 1) {
+        imageStore(aovImage, pos, vec4(compute_aov(inter, primary_rays[idx]), 1.0));
+    }
+    else {
+        imageStore(aovImage, pos, vec4(0.0));
+    }
+}
@@ -4,8 +4,6 @@
 
 #include "include/defs.glsl"
 
-layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
-
 layout(constant_id = 0) const float CHESSBOARD_SCALE = 1.0;
 
 layout(set = 0, binding = 0) readonly uniform Info {
@@ -40,20 +38,32 @@ layout(std140, set = 1, binding = 4) readonly buffer HitBoxes {
     HitBoxRectangle[] hit_boxes;
 };
 
+#include "include/tile.glsl"
+
 void main() {
-    uint idx = gl_GlobalInvocationID.x;
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
 
     Intersection inter = intersections[idx];
 
-    ivec2 pos = ivec2(idx % screen.x, idx / screen.x);
-
     if (inter.is_intersect == 1) {
-        uvec3 index = indexes[inter.triangle_idx];
+        ModelInfo model = models[inter.model_id];
+        vec3 local_coords;
+
+        if (model.primitive_idx != NO_PRIMITIVE) {
+            local_coords = (model.world_to_model * vec4(inter.point, 1.0)).xyz;
+        }
+        else {
+            uvec3 index = indexes[inter.triangle_idx];
 
-        vec3 local_coords =
-            vertices[inter.vertices_offset + index.y] * inter.barycentric_coords.x +
-            vertices[inter.vertices_offset + index.z] * inter.barycentric_coords.y +
-            vertices[inter.vertices_offset + index.x] * (1 - inter.barycentric_coords.x - inter.barycentric_coords.y);
+            local_coords =
+                vertices[inter.vertices_offset + index.y] * inter.barycentric_coords.x +
+                vertices[inter.vertices_offset + index.z] * inter.barycentric_coords.y +
+                vertices[inter.vertices_offset + index.x] * (1 - inter.barycentric_coords.x - inter.barycentric_coords.y);
+        }
 
         local_coords = local_coords / CHESSBOARD_SCALE;
 
@@ -4,8 +4,6 @@
 
 #include "include/defs.glsl"
 
-layout(local_size_x_id = 0, local_size_y = 1, local_size_z = 1) in;
-
 layout(set = 0, binding = 0) readonly uniform Info {
     uvec2 screen;
 };
@@ -13,6 +11,13 @@ layout(std140, set = 0, binding = 1) readonly uniform Camera {
     vec3 pos;
     mat3 rotation;
     float fov;
+    vec3 end_pos;
+    // Rotations at the opening and the closing of the shutter as quaternions.
+    vec4 orientation;
+    vec4 end_orientation;
+    // Sub-frame of a motion blurred frame, every one covers its own part of the shutter.
+    uint shutter_sample;
+    uint shutter_samples;
 };
 layout(std140, set = 0, binding = 2) writeonly buffer Rays {
     Ray rays[];
@@ -22,31 +27,35 @@ layout(std140, set = 0, binding = 3) readonly buffer Intersections {
 };
 layout(set = 0, binding = 4, rgba8) readonly uniform image2D resultImage;
 
-uint compute_x(uint screen_width) {
-    return gl_GlobalInvocationID.x % screen_width;
-}
-
-uint compute_y(uint screen_width) {
-    return gl_GlobalInvocationID.x / screen_width;
-}
+#include "include/tile.glsl"
+#include "include/sampling.glsl"
+#include "include/quaternion.glsl"
 
 void main() {
     uint screen_width = screen.x;
     uint screen_height = screen.y;
 
-    uint idx = gl_GlobalInvocationID.x;
+    ivec2 pixel;
+    uint idx;
+    if (!pixel_of_invocation(pixel, idx)) {
+        return;
+    }
 
-    vec2 this_point = vec2(compute_x(screen_width), compute_y(screen_width));
+    vec2 this_point = vec2(pixel);
 
     float scale = tan(fov / 2);
     float aspect_ratio = float(screen_width) / float(screen_height);
 
-    vec3 origin = pos;
+    seed_random(idx + shutter_sample * screen_width * screen_height, RANDOM_STREAM_CAMERA);
+    float time = (float(shutter_sample) + random()) / float(shutter_samples);
+
+    vec3 origin = mix(pos, end_pos, time);
+    mat3 camera_rotation = quaternion_to_mat3(slerp(orientation, end_orientation, time));
 
     float x = (2 * ((this_point.x + 0.5) / float(screen_width)) - 1) * aspect_ratio * scale;
     float y = (1 - 2 * ((this_point.y + 0.5) / float(screen_height))) * scale;
 
-    vec4 direction = vec4(normalize(rotation * vec3(x, y, -1.0)), 0.0);
+    vec4 direction = vec4(normalize(camera_rotation * vec3(x, y, -1.0)), 0.0);
 
-    rays[idx] = Ray(origin, direction, 1.0 / 0.0);
+    rays[idx] = Ray(origin, direction, 1.0 / 0.0, time);
 }
\ No newline at end of file
@@ -0,0 +1,186 @@
+#version 450
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "include/defs.glsl"
+
+// set0 is global for app
+layout(set = 0, binding = 0) readonly uniform Info {
+    uvec2 screen;
+};
+layout(std140, set = 0, binding = 1) readonly uniform Camera {
+    vec3 pos;
+    mat3 rotation;
+    float fov;
+};
+layout(std140, set = 0, binding = 2) readonly buffer PrimaryRays {
+    Ray primary_rays[];
+};
+layout(std140, set = 0, binding = 3) readonly buffer PrimaryIntersections {
+    Intersection primary_rays_intersections[];
+};
+layout(set = 0, binding = 4, rgba8) writeonly uniform image2D resultImage;
+
+// set1 for models
+layout(std140, set = 1, binding = 0) readonly uniform SceneInfo {
+    uint model_counts;
+};
+layout(std140, set = 1, binding = 1) readonly buffer ModelInfos {
+    ModelInfo[] models;
+};
+layout(set = 1, binding = 2) readonly buffer Vertices {
+    vec3[] vertices;
+};
+layout(std140, set = 1, binding = 3) readonly buffer Indexes {
+    uvec3[] indexes;
+};
+layout(std140, set = 1, binding = 4) readonly buffer HitBoxes {
+    HitBoxRectangle[] hit_boxes;
+};
+layout(std140, set = 1, binding = 5) readonly buffer Primitives {
+    Primitive[] primitives;
+};
+layout(set = 1, binding = 6) readonly buffer Normals {
+    vec3[] normals;
+};
+layout(set = 1, binding = 7) readonly buffer Uvs {
+    vec2[] uvs;
+};
+
+// set2 for lights
+layout(std140, set = 2, binding = 0) readonly uniform DirectionLightsInfo {
+    uint direction_lights_count;
+};
+layout(std140, set = 2, binding = 1) readonly buffer DirectionLights {
+    DirectLight[] direction_lights;
+};
+layout(std140, set = 2, binding = 2) readonly uniform PointLightsInfo {
+    uint point_lights_count;
+};
+layout(std140, set = 2, binding = 3) readonly buffer PointLights {
+    PointLight[] point_lights;
+};
+layout(std140, set = 2, binding = 8) readonly uniform SpotLightsInfo {
+    uint spot_lights_count;
+};
+layout(std140, set = 2, binding = 9) readonly buffer SpotLights {
+    SpotLight[] spot_lights;
+};
+
+// set3 for the debug view
+layout(std140, set = 3, binding = 0) readonly uniform DebugInfo {
+    uint view;
+    float checkerboard_scale;
+};
+
+#include "include/tile.glsl"
+#include "include/quaternion.glsl"
+#include "include/ray_tracing.glsl"
+
+// Must match rencan_core::debug_view::DebugView
+#define VIEW_NORMALS 1u
+#define VIEW_FACING_RATIO 2u
+#define VIEW_CHECKERBOARD 3u
+#define VIEW_BARYCENTRICS 4u
+#define VIEW_TRIANGLE_ID 5u
+#define VIEW_MODEL_ID 6u
+#define VIEW_SHADOW_RAY_COUNT 7u
+#define VIEW_REFLECTION_MASK 8u
+
+vec3 id_color(uint id) {
+    uint hash = (id + 1) * 2654435761u;
+    return vec3(hash & 255u, (hash >> 8) & 255u, (hash >> 16) & 255u) / 255.0;
+}
+
+vec3 checkerboard(Intersection inter) {
+    ModelInfo model = models[inter.model_id];
+    vec3 local_coords;
+
+    if (model.primitive_idx != NO_PRIMITIVE) {
+        local_coords = (model.world_to_model * vec4(inter.point, 1.0)).xyz;
+    }
+    else {
+        uvec3 index = indexes[inter.triangle_idx];
+
+        local_coords =
+            vertices[inter.vertices_offset + index.y] * inter.barycentric_coords.x +
+            vertices[inter.vertices_offset + index.z] * inter.barycentric_coords.y +
+            vertices[inter.vertices_offset + index.x] * (1 - inter.barycentric_coords.x - inter.barycentric_coords.y);
+    }
+
+    local_coords = local_coords / checkerboard_scale;
+
+    float chessboard = fract((floor(local_coords.x) + floor(local_coords.y) + floor(local_coords.z)) * 0.5);
+    return vec3(chessboard * 2);
+}
+
+vec3 shadow_ray_count(Intersection inter) {
+    vec3 point = offset_ray(inter.point, inter.normal);
+    uint occluded = 0;
+
+    for (int i = 0; i < direction_lights_count; i++) {
+        DirectLight light = direction_lights[i];
+        if (light.cast_shadows == 1 && trace_any(Ray(point, vec4(-light.direction, 0.0), 1.0 / 0.0, shutter_time), VISIBLE_SHADOW).is_intersect == 1) {
+            occluded++;
+        }
+    }
+    for (int i = 0; i < point_lights_count; i++) {
+        vec3 direction = point_lights[i].position - inter.point;
+        if (trace_any(Ray(point, vec4(normalize(direction), 0.0), length(direction), shutter_time), VISIBLE_SHADOW).is_intersect == 1) {
+            occluded++;
+        }
+    }
+    for (int i = 0; i < spot_lights_count; i++) {
+        vec3 direction = spot_lights[i].position - inter.point;
+        if (trace_any(Ray(point, vec4(normalize(direction), 0.0), length(direction), shutter_time), VISIBLE_SHADOW).is_intersect == 1) {
+            occluded++;
+        }
+    }
+
+    uint lights_count = direction_lights_count + point_lights_count + spot_lights_count;
+    float heat = float(occluded) / float(max(lights_count, 1u));
+    return mix(vec3(0.0, 0.0, 1.0), vec3(1.0, 0.0, 0.0), heat);
+}
+
+vec3 debug_color(Intersection inter, Ray primary_ray) {
+    switch (view) {
+        case VIEW_NORMALS:
+            return inter.normal * 0.5 + 0.5;
+        case VIEW_FACING_RATIO:
+            return vec3(max(dot(inter.normal, -primary_ray.direction.xyz), 0.0));
+        case VIEW_CHECKERBOARD:
+            return checkerboard(inter);
+        case VIEW_BARYCENTRICS:
+            return vec3(
+                1 - inter.barycentric_coords.x - inter.barycentric_coords.y,
+                inter.barycentric_coords
+            );
+        case VIEW_TRIANGLE_ID:
+            return id_color(inter.triangle_idx);
+        case VIEW_MODEL_ID:
+            return id_color(inter.model_handle);
+        case VIEW_SHADOW_RAY_COUNT:
+            return shadow_ray_count(inter);
+        case VIEW_REFLECTION_MASK:
+            return vec3(models[inter.model_id].specularity);
+    }
+    return vec3(1.0, 0.0, 1.0);
+}
+
+void main() {
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
+
+    Intersection inter = primary_rays_intersections[idx];
+    shutter_time = primary_rays[idx].time;
+
+    if (inter.is_intersect == 1) {
+        imageStore(resultImage, pos, vec4(debug_color(inter, primary_rays[idx]), 0.0));
+    }
+    else {
+        imageStore(resultImage, pos, vec4(0.0));
+    }
+}
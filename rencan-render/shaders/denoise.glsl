@@ -0,0 +1,105 @@
+#version 450
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "include/defs.glsl"
+
+// set0 is global for app
+layout(set = 0, binding = 0) readonly uniform Info {
+    uvec2 screen;
+};
+layout(std140, set = 0, binding = 1) readonly uniform Camera {
+    vec3 pos;
+    mat3 rotation;
+    float fov;
+};
+layout(std140, set = 0, binding = 2) readonly buffer PrimaryRays {
+    Ray primary_rays[];
+};
+layout(std140, set = 0, binding = 3) readonly buffer PrimaryIntersections {
+    Intersection primary_rays_intersections[];
+};
+
+// set1 for models
+layout(std140, set = 1, binding = 0) readonly uniform SceneInfo {
+    uint model_counts;
+};
+layout(std140, set = 1, binding = 1) readonly buffer ModelInfos {
+    ModelInfo[] models;
+};
+
+// set2 for the filter pass
+layout(std140, set = 2, binding = 0) readonly uniform Params {
+    // 0 means copying of the source image without filtering
+    int step_width;
+    float sigma_color;
+    float sigma_normal;
+    float sigma_depth;
+    float sigma_albedo;
+};
+layout(set = 2, binding = 1, rgba8) readonly uniform image2D sourceImage;
+layout(set = 2, binding = 2, rgba8) writeonly uniform image2D resultImage;
+
+#include "include/tile.glsl"
+
+const float kernel[3] = float[](3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0);
+
+struct Guide {
+    vec3 normal;
+    float depth;
+    float albedo;
+};
+
+Guide guide_of(uint idx) {
+    Intersection inter = primary_rays_intersections[idx];
+    if (inter.is_intersect == 0) {
+        return Guide(vec3(0.0), 1.0 / 0.0, 0.0);
+    }
+    return Guide(inter.normal, inter.distance, models[inter.model_id].albedo);
+}
+
+void main() {
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
+
+    vec4 center_color = imageLoad(sourceImage, pos);
+
+    Guide center = guide_of(idx);
+
+    // Background is left as is
+    if (step_width == 0 || isinf(center.depth)) {
+        imageStore(resultImage, pos, center_color);
+        return;
+    }
+
+    vec4 sum = vec4(0.0);
+    float weights = 0.0;
+
+    for (int y = -2; y <= 2; y++) {
+        for (int x = -2; x <= 2; x++) {
+            ivec2 sample_pos = pos + ivec2(x, y) * step_width;
+            if (any(lessThan(sample_pos, ivec2(0))) || any(greaterThanEqual(sample_pos, ivec2(screen)))) {
+                continue;
+            }
+            vec4 color = imageLoad(sourceImage, sample_pos);
+            Guide guide = guide_of(uint(sample_pos.y) * screen.x + uint(sample_pos.x));
+
+            vec3 color_diff = color.rgb - center_color.rgb;
+            float w_color = exp(-dot(color_diff, color_diff) / (sigma_color * sigma_color));
+            float w_normal = pow(max(dot(center.normal, guide.normal), 0.0), sigma_normal);
+            float w_depth = exp(-abs(center.depth - guide.depth) / (sigma_depth * step_width));
+            float albedo_diff = center.albedo - guide.albedo;
+            float w_albedo = exp(-albedo_diff * albedo_diff / (sigma_albedo * sigma_albedo));
+
+            float w = kernel[abs(x)] * kernel[abs(y)] * w_color * w_normal * w_depth * w_albedo;
+
+            sum += color * w;
+            weights += w;
+        }
+    }
+
+    imageStore(resultImage, pos, sum / max(weights, 0.0001));
+}
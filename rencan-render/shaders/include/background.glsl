@@ -0,0 +1,60 @@
+// Expects `Background background`, `background_pixels` and `direction_lights` to be declared.
+
+// Must match rencan_core::background::Background
+#define BACKGROUND_COLOR 0u
+#define BACKGROUND_GRADIENT 1u
+#define BACKGROUND_EQUIRECTANGULAR 2u
+#define BACKGROUND_SKY 3u
+
+#define BACKGROUND_PI radians(180)
+
+vec3 equirectangular_color(vec3 direction) {
+    float u = atan(direction.x, -direction.z) / (2 * BACKGROUND_PI) + 0.5;
+    float v = acos(clamp(direction.y, -1.0, 1.0)) / BACKGROUND_PI;
+
+    uvec2 texel = min(uvec2(vec2(u, v) * vec2(background.size)), background.size - 1u);
+    return background_pixels[texel.y * background.size.x + texel.x].rgb * background.intensity;
+}
+
+vec3 sky_color(vec3 direction) {
+    vec3 zenith = background.color_a;
+    vec3 horizon = background.color_b;
+
+    if (direction.y < 0.0) {
+        // Darkened ground, blended with the horizon to avoid a hard edge.
+        return mix(horizon, horizon * 0.3, min(-direction.y * 8.0, 1.0));
+    }
+
+    vec3 color = mix(horizon, zenith, sqrt(direction.y));
+    if (direction_lights_count == 0) {
+        return color;
+    }
+
+    // The first direction light is the sun.
+    vec3 sun_direction = normalize(-direction_lights[0].direction);
+    vec3 sun = direction_lights[0].color * direction_lights[0].intensity;
+
+    // Brighter sky towards the sun and the solar disk of ~0.5 degrees.
+    float cos_to_sun = dot(direction, sun_direction);
+    color += sun * 0.05 * pow(max(cos_to_sun, 0.0), 64.0);
+    if (cos_to_sun > 0.99996) {
+        color += sun;
+    }
+
+    return color;
+}
+
+vec3 background_color(vec3 direction) {
+    direction = normalize(direction);
+    switch (background.kind) {
+        case BACKGROUND_COLOR:
+            return background.color_a;
+        case BACKGROUND_GRADIENT:
+            return mix(background.color_b, background.color_a, direction.y * 0.5 + 0.5);
+        case BACKGROUND_EQUIRECTANGULAR:
+            return equirectangular_color(direction);
+        case BACKGROUND_SKY:
+            return sky_color(direction);
+    }
+    return vec3(0.0);
+}
@@ -2,48 +2,130 @@ struct Ray {
     vec3 origin;
     vec4 direction;
     float max_distance;
+    // Moment in the shutter interval, from 0 at the opening to 1 at the closing.
+    float time;
 };
 
 struct Intersection {
     vec3 point;
+    // Faces the origin of the ray.
     vec3 normal;
     vec2 barycentric_coords;
     uint is_intersect;
+    // Index in the models buffer.
     uint model_id;
     uint triangle_idx;
     uint vertices_offset;
     float distance;
+    // Stable ModelInfo::model_id of the model.
+    uint model_handle;
+    // 1 if the ray hit the front face, i.e. the normal is not flipped.
+    uint front_face;
 };
 
 Intersection intersection_succ(
     vec3 point,
     vec3 normal,
     uint model_id,
+    uint model_handle,
     uint triangle_idx,
     uint vertices_offset,
     vec2 barycentric_coords,
-    float distance
+    float distance,
+    bool front_face
 ) {
-    return Intersection(point, normal, barycentric_coords, 1, model_id, triangle_idx, vertices_offset, distance);
+    return Intersection(point, normal, barycentric_coords, 1, model_id, triangle_idx, vertices_offset, distance, model_handle, uint(front_face));
 }
 
 Intersection intersection_none() {
-    return Intersection(vec3(0.0), vec3(0.0), vec2(0.0), 0, 0, 0, 0, 0.0);
+    return Intersection(vec3(0.0), vec3(0.0), vec2(0.0), 0, 0, 0, 0, 0.0, 0, 0);
 }
 
 struct DirectLight {
     vec3 color;
     vec3 direction;
     float intensity;
+    float angular_diameter;
+    uint cast_shadows;
+};
+
+struct Background {
+    vec3 color_a;
+    uint kind;
+    vec3 color_b;
+    float intensity;
+    uvec2 size;
+    uint image_based_lighting;
+};
+
+struct Medium {
+    vec3 absorption;
+    uint enabled;
+    vec3 scattering;
+    float anisotropy;
+    float max_distance;
+    uint steps;
 };
 
 struct ModelInfo {
-    mat4 isometry;
+    mat4 model_to_world;
+    mat4 world_to_model;
+    mat3 normal_to_world;
+    // `model_to_world` of a moving model split into a translation, a rotation and a stretch at
+    // the opening and the closing of the shutter, see `_motion_transforms`.
+    vec3 start_translation;
+    vec4 start_rotation;
+    mat3 start_stretch;
+    vec3 end_translation;
+    vec4 end_rotation;
+    mat3 end_stretch;
+    uint moving;
     uint model_id;
     uint vertices_length;
     uint indexes_length;
     float albedo;
     float specularity;
+    uint visibility;
+    uint cull_mode;
+    vec3 emission;
+    uint primitive_idx;
+};
+
+// Must match rencan_core::model::Visibility
+#define VISIBLE_CAMERA 1u
+#define VISIBLE_SHADOW 2u
+#define VISIBLE_REFLECTION 4u
+#define VISIBLE_ALL 7u
+
+// Must match rencan_core::model::CullMode
+#define CULL_NONE 0u
+#define CULL_BACK 1u
+#define CULL_FRONT 2u
+
+// Must match rencan_core::primitive::NO_PRIMITIVE
+#define NO_PRIMITIVE 0xFFFFFFFFu
+
+// Must match rencan_core::primitive::Primitive
+#define PRIMITIVE_SPHERE 0u
+#define PRIMITIVE_PLANE 1u
+#define PRIMITIVE_BOX 2u
+#define PRIMITIVE_DISK 3u
+#define PRIMITIVE_CYLINDER 4u
+
+struct Primitive {
+    vec3 size;
+    uint kind;
+};
+
+struct Emitter {
+    vec3 v0;
+    float cdf;
+    vec3 v1;
+    float area;
+    vec3 v2;
+    float probability;
+    vec3 radiance;
+    uint model_id;
 };
 
 struct HitBoxRectangle {
@@ -55,4 +137,16 @@ struct PointLight {
     vec3 color;
     vec3 position;
     float intensity;
+    float radius;
+};
+
+struct SpotLight {
+    vec3 color;
+    vec3 position;
+    float intensity;
+    vec3 direction;
+    float cos_inner;
+    float cos_outer;
+    float radius;
+    uint profile_index;
 };
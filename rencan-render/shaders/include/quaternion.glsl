@@ -0,0 +1,25 @@
+// Unit quaternions as vec4 with the real part in `w`, the same as nalgebra stores them.
+
+vec4 slerp(vec4 a, vec4 b, float t) {
+    float cos_theta = dot(a, b);
+    // `b` and `-b` are the same rotation, the shorter arc is taken
+    if (cos_theta < 0.0) {
+        b = -b;
+        cos_theta = -cos_theta;
+    }
+    // nearly equal rotations, `sin(theta)` is too close to zero
+    if (cos_theta > 0.9995) {
+        return normalize(mix(a, b, t));
+    }
+    float theta = acos(cos_theta);
+    return (sin((1.0 - t) * theta) * a + sin(t * theta) * b) / sin(theta);
+}
+
+mat3 quaternion_to_mat3(vec4 q) {
+    float x = q.x, y = q.y, z = q.z, w = q.w;
+    return mat3(
+        1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + w * z), 2.0 * (x * z - w * y),
+        2.0 * (x * y - w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + w * x),
+        2.0 * (x * z + w * y), 2.0 * (y * z - w * x), 1.0 - 2.0 * (x * x + y * y)
+    );
+}
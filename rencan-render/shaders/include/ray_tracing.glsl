@@ -1,5 +1,9 @@
 const float eps = 0.0001;
 
+// Time of the camera ray being shaded, secondary rays are traced at the same moment. Set by
+// `main` from the primary ray.
+float shutter_time = 0.0;
+
 struct IntersectResult {
     vec3 normal;
     vec2 barycentric_coords;
@@ -15,30 +19,203 @@ IntersectResult ret_intersect(vec3 normal, vec2 coords, float t) {
     return IntersectResult(normal, coords, t, true);
 }
 
+// Offsets the origin of a ray leaving the surface at the point along the normal, which must
+// face the side of the new ray. The offset grows with the magnitude of the coordinates like
+// their rounding error, so there is neither self-intersection nor light leaking at any scale.
+// "A Fast and Robust Method for Avoiding Self-Intersection", Ray Tracing Gems, chapter 6.
+// Must match rencan_core::ray::Ray::offset_origin.
+vec3 offset_ray(vec3 point, vec3 normal) {
+    const float origin = 1.0 / 32.0;
+    const float float_scale = 1.0 / 65536.0;
+    const float int_scale = 256.0;
+
+    ivec3 offset_int = ivec3(int_scale * normal);
+    vec3 point_int = vec3(
+        intBitsToFloat(floatBitsToInt(point.x) + (point.x < 0.0 ? -offset_int.x : offset_int.x)),
+        intBitsToFloat(floatBitsToInt(point.y) + (point.y < 0.0 ? -offset_int.y : offset_int.y)),
+        intBitsToFloat(floatBitsToInt(point.z) + (point.z < 0.0 ? -offset_int.z : offset_int.z))
+    );
+    // Near the origin the ulps are too small, so a fixed offset is used.
+    return vec3(
+        abs(point.x) < origin ? point.x + float_scale * normal.x : point_int.x,
+        abs(point.y) < origin ? point.y + float_scale * normal.y : point_int.y,
+        abs(point.z) < origin ? point.z + float_scale * normal.z : point_int.z
+    );
+}
+
+// Watertight ray-triangle intersection, rays never pass between triangles with a shared edge.
+// "Watertight Ray/Triangle Intersection", Woop, Benthin and Wald, 2013. Both faces are hit,
+// `_trace` culls them.
 IntersectResult _intersect(Ray ray, vec3[3] triangle) {
-    vec3 v0v1 = triangle[1] - triangle[0];
-    vec3 v0v2 = triangle[2] - triangle[0];
-    vec3 pvec = cross(ray.direction.xyz, v0v2);
-    float det = dot(v0v1, cross(ray.direction.xyz, v0v2));
+    vec3 direction = ray.direction.xyz;
+
+    // The axis along which the direction is the longest becomes z.
+    vec3 abs_direction = abs(direction);
+    int kz = abs_direction.x > abs_direction.y
+        ? (abs_direction.x > abs_direction.z ? 0 : 2)
+        : (abs_direction.y > abs_direction.z ? 1 : 2);
+    int kx = (kz + 1) % 3;
+    int ky = (kx + 1) % 3;
+    // keeps the winding of the triangle
+    if (direction[kz] < 0.0) {
+        int k = kx;
+        kx = ky;
+        ky = k;
+    }
+
+    // Shear and scale the vertices so that the ray goes along z from the origin.
+    float sz = 1.0 / direction[kz];
+    float sx = direction[kx] * sz;
+    float sy = direction[ky] * sz;
+
+    vec3 a = triangle[0] - ray.origin;
+    vec3 b = triangle[1] - ray.origin;
+    vec3 c = triangle[2] - ray.origin;
+
+    float ax = a[kx] - sx * a[kz];
+    float ay = a[ky] - sy * a[kz];
+    float bx = b[kx] - sx * b[kz];
+    float by = b[ky] - sy * b[kz];
+    float cx = c[kx] - sx * c[kz];
+    float cy = c[ky] - sy * c[kz];
 
-    if (det < eps) return not_intersect();
+    // Edge functions, i.e. unnormalized barycentric coordinates of the vertices a, b and c.
+    float u = cx * by - cy * bx;
+    float v = ax * cy - ay * cx;
+    float w = bx * ay - by * ax;
+
+    if ((u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0)) return not_intersect();
+
+    float det = u + v + w;
+    if (det == 0.0) return not_intersect();
+
+    float t_scaled = (u * a[kz] + v * b[kz] + w * c[kz]) * sz;
+    if ((det < 0.0 && t_scaled >= 0.0) || (det > 0.0 && t_scaled <= 0.0)) return not_intersect();
 
     float inv_det = 1.0 / det;
+    vec3 normal = normalize(cross(triangle[1] - triangle[0], triangle[2] - triangle[0]));
+
+    return ret_intersect(normal, vec2(v, w) * inv_det, t_scaled * inv_det);
+}
+
+// Smallest positive root of `a * t^2 + 2 * b * t + c = 0`, negative if there is none.
+float _nearest_root(float a, float b, float c) {
+    float discriminant = b * b - a * c;
+    if (discriminant < 0.0) {
+        return -1.0;
+    }
+    float root = sqrt(discriminant);
+    float t = (-b - root) / a;
+    return t > 0.0 ? t : (-b + root) / a;
+}
+
+// The ray direction is not normalized in the model space of scaled models, so all distances
+// are in the units of the world space ray.
+IntersectResult _intersect_sphere(Ray ray, float radius) {
+    vec3 direction = ray.direction.xyz;
+    float t = _nearest_root(
+        dot(direction, direction),
+        dot(ray.origin, direction),
+        dot(ray.origin, ray.origin) - radius * radius
+    );
+    if (t <= 0.0) return not_intersect();
+
+    vec3 point = ray.origin + direction * t;
+    return ret_intersect(point / radius, vec2(0.0), t);
+}
+
+IntersectResult _intersect_plane(Ray ray) {
+    if (abs(ray.direction.y) < eps) return not_intersect();
+
+    float t = -ray.origin.y / ray.direction.y;
+    if (t <= 0.0) return not_intersect();
+
+    // Both sides of the plane are front faces.
+    vec3 normal = vec3(0.0, ray.origin.y >= 0.0 ? 1.0 : -1.0, 0.0);
+    return ret_intersect(normal, vec2(0.0), t);
+}
+
+IntersectResult _intersect_disk(Ray ray, float radius) {
+    IntersectResult res = _intersect_plane(ray);
+    if (!res.intersect) return res;
+
+    vec3 point = ray.origin + ray.direction.xyz * res.distance;
+    if (dot(point.xz, point.xz) > radius * radius) return not_intersect();
+    return res;
+}
+
+IntersectResult _intersect_cuboid(Ray ray, vec3 half_extents) {
+    vec3 m = 1.0 / ray.direction.xyz;
+    vec3 n = m * ray.origin;
+    vec3 k = abs(m) * half_extents;
+    vec3 t1 = -n - k;
+    vec3 t2 = -n + k;
+
+    float t_near = max(max(t1.x, t1.y), t1.z);
+    float t_far = min(min(t2.x, t2.y), t2.z);
+    if (t_near > t_far || t_far <= 0.0) return not_intersect();
+
+    float t = t_near > 0.0 ? t_near : t_far;
+    vec3 point = (ray.origin + ray.direction.xyz * t) / half_extents;
+    vec3 distances = abs(point);
+    vec3 normal;
+    if (distances.x > distances.y && distances.x > distances.z) {
+        normal = vec3(sign(point.x), 0.0, 0.0);
+    }
+    else if (distances.y > distances.z) {
+        normal = vec3(0.0, sign(point.y), 0.0);
+    }
+    else {
+        normal = vec3(0.0, 0.0, sign(point.z));
+    }
+    return ret_intersect(normal, vec2(0.0), t);
+}
 
-    vec3 tvec = ray.origin - triangle[0];
-    float u = dot(tvec, pvec) * inv_det;
-    if (u < 0 || u > 1) return not_intersect();
+IntersectResult _intersect_cylinder(Ray ray, float radius, float half_height) {
+    vec3 direction = ray.direction.xyz;
+    IntersectResult res = not_intersect();
+    float distance = 1.0 / 0.0;
 
-    vec3 vvec = cross(tvec, v0v1);
-    float v = dot(ray.direction.xyz, vvec) * inv_det;
-    if (v < 0 || u + v > 1) return not_intersect();
+    float t = _nearest_root(
+        dot(direction.xz, direction.xz),
+        dot(ray.origin.xz, direction.xz),
+        dot(ray.origin.xz, ray.origin.xz) - radius * radius
+    );
+    vec3 point = ray.origin + direction * t;
+    if (t > 0.0 && abs(point.y) <= half_height) {
+        res = ret_intersect(vec3(point.x, 0.0, point.z) / radius, vec2(0.0), t);
+        distance = t;
+    }
 
-    float t = dot(v0v2, vvec) * inv_det;
-    if (t < 0) return not_intersect();
+    if (abs(direction.y) >= eps) {
+        for (int side = -1; side <= 1; side += 2) {
+            float t_cap = (float(side) * half_height - ray.origin.y) / direction.y;
+            vec3 cap_point = ray.origin + direction * t_cap;
+            if (t_cap > 0.0 && t_cap < distance && dot(cap_point.xz, cap_point.xz) <= radius * radius) {
+                res = ret_intersect(vec3(0.0, float(side), 0.0), vec2(0.0), t_cap);
+                distance = t_cap;
+            }
+        }
+    }
 
-    vec3 normal = normalize(cross(v0v1, v0v2));
+    return res;
+}
 
-    return ret_intersect(normal, vec2(u, v), t);
+// Returns the normal in the model space.
+IntersectResult _intersect_primitive(Ray ray, Primitive primitive) {
+    switch (primitive.kind) {
+        case PRIMITIVE_SPHERE:
+            return _intersect_sphere(ray, primitive.size.x);
+        case PRIMITIVE_PLANE:
+            return _intersect_plane(ray);
+        case PRIMITIVE_BOX:
+            return _intersect_cuboid(ray, primitive.size);
+        case PRIMITIVE_DISK:
+            return _intersect_disk(ray, primitive.size.x);
+        case PRIMITIVE_CYLINDER:
+            return _intersect_cylinder(ray, primitive.size.x, primitive.size.y);
+    }
+    return not_intersect();
 }
 
 vec3 _intersect_box(HitBoxRectangle hit_box, Ray ray) {
@@ -59,8 +236,51 @@ vec3 _intersect_box(HitBoxRectangle hit_box, Ray ray) {
     return vec3(1.0, tN, tF);
 }
 
-Intersection trace(
-    Ray origin_ray
+// Whether the hit face is skipped by the model, occlusion (any hit) queries like shadow rays hit
+// both faces. Primitives are culled by their nearest hit only.
+bool _culled(uint cull_mode, bool front_face, bool any_hit) {
+    if (any_hit) {
+        return false;
+    }
+    return (cull_mode == CULL_BACK && !front_face) || (cull_mode == CULL_FRONT && front_face);
+}
+
+// Normal of the triangle interpolated from the normals of its vertices, `geometric` if the
+// vertices have no normals. Always on the side of the `geometric` normal.
+vec3 _shading_normal(vec3 geometric, uvec3 index, uint offset_vertices, vec2 coords, mat3 normal_to_world) {
+    vec3 normal = (1.0 - coords.x - coords.y) * normals[offset_vertices + index.x] +
+        coords.x * normals[offset_vertices + index.y] +
+        coords.y * normals[offset_vertices + index.z];
+    if (dot(normal, normal) == 0.0) {
+        return geometric;
+    }
+    normal = normalize(normal_to_world * normal);
+    return dot(normal, geometric) < 0.0 ? -normal : normal;
+}
+
+// Transforms of a moving model at the time in the shutter interval. Translations and stretches
+// are interpolated linearly and rotations spherically, so rotating models keep their shape.
+void _motion_transforms(ModelInfo model, float t, out mat4 world_to_model, out mat3 normal_to_world) {
+    mat3 rotation = quaternion_to_mat3(slerp(model.start_rotation, model.end_rotation, t));
+    mat3 linear = rotation * (model.start_stretch * (1.0 - t) + model.end_stretch * t);
+    // Singular transforms flatten the model, zero matrices make it impossible to hit.
+    if (determinant(linear) == 0.0) {
+        world_to_model = mat4(0.0);
+        normal_to_world = mat3(0.0);
+        return;
+    }
+    mat3 inverse_linear = inverse(linear);
+    vec3 translation = mix(model.start_translation, model.end_translation, t);
+    world_to_model = mat4(inverse_linear);
+    world_to_model[3] = vec4(-(inverse_linear * translation), 1.0);
+    normal_to_world = transpose(inverse_linear);
+}
+
+// Finds the closest intersection if any_hit is false, otherwise returns the first found one.
+Intersection _trace(
+    Ray origin_ray,
+    bool any_hit,
+    uint visibility
 ) {
     Intersection inter = intersection_none();
     float distance = 1.0 / 0.0;
@@ -74,9 +294,47 @@ Intersection trace(
         HitBoxRectangle hit_box = hit_boxes[model_idx];
         ModelInfo model = models[model_idx];
 
-        mat4 global_to_model = inverse(model.isometry);
-        ray.origin = (global_to_model * vec4(origin_ray.origin, 1.0)).xyz;
-        ray.direction = global_to_model * origin_ray.direction;
+        if ((model.visibility & visibility) == 0u) {
+            offset_indexes += model.indexes_length;
+            offset_vertices += model.vertices_length;
+            continue;
+        }
+
+        mat4 world_to_model = model.world_to_model;
+        mat3 normal_to_world = model.normal_to_world;
+        if (model.moving == 1u) {
+            _motion_transforms(model, origin_ray.time, world_to_model, normal_to_world);
+        }
+        ray.origin = (world_to_model * vec4(origin_ray.origin, 1.0)).xyz;
+        ray.direction = world_to_model * origin_ray.direction;
+
+        if (model.primitive_idx != NO_PRIMITIVE) {
+            IntersectResult res = _intersect_primitive(ray, primitives[model.primitive_idx]);
+            if (res.intersect && res.distance < distance && res.distance < ray.max_distance) {
+                vec3 normal = normalize(normal_to_world * res.normal);
+                bool front_face = dot(normal, origin_ray.direction.xyz) < 0.0;
+                if (!_culled(model.cull_mode, front_face, any_hit)) {
+                    vec3 inter_point = origin_ray.origin + origin_ray.direction.xyz * res.distance;
+                    distance = res.distance;
+                    inter = intersection_succ(
+                        inter_point,
+                        front_face ? normal : -normal,
+                        model_idx,
+                        model.model_id,
+                        0,
+                        offset_vertices,
+                        res.barycentric_coords,
+                        res.distance,
+                        front_face
+                    );
+                    if (any_hit) {
+                        return inter;
+                    }
+                }
+            }
+            // Primitives have no vertices and indexes, so the offsets stay the same.
+            continue;
+        }
 
         vec3 is_inter_hitbox = _intersect_box(hit_box, ray);
 
@@ -94,17 +352,28 @@ Intersection trace(
             vec3[3] vertices = vec3[](vertice1, vertice2, vertice3);
             IntersectResult res = _intersect(ray, vertices);
             if (res.intersect && res.distance < distance && res.distance < ray.max_distance) {
+                vec3 normal = normalize(normal_to_world * res.normal);
+                bool front_face = dot(normal, origin_ray.direction.xyz) < 0.0;
+                if (_culled(model.cull_mode, front_face, any_hit)) {
+                    continue;
+                }
                 vec3 inter_point = origin_ray.origin + origin_ray.direction.xyz * res.distance;
                 distance = res.distance;
+                normal = _shading_normal(normal, index, offset_vertices, res.barycentric_coords, normal_to_world);
                 inter = intersection_succ(
                     inter_point,
-                    res.normal,
+                    front_face ? normal : -normal,
                     model_idx,
+                    model.model_id,
                     offset_indexes + i,
                     offset_vertices,
                     res.barycentric_coords,
-                    res.distance
+                    res.distance,
+                    front_face
                 );
+                if (any_hit) {
+                    return inter;
+                }
             }
         }
         offset_indexes += model.indexes_length;
@@ -113,3 +382,13 @@ Intersection trace(
 
     return inter;
 }
+
+// `visibility` is one of VISIBLE_*, models which are not visible for it are skipped.
+Intersection trace(Ray ray, uint visibility) {
+    return _trace(ray, false, visibility);
+}
+
+// Faster than trace() when only the fact of an intersection is needed, e.g. for shadow rays.
+Intersection trace_any(Ray ray, uint visibility) {
+    return _trace(ray, true, visibility);
+}
@@ -0,0 +1,90 @@
+// Per-invocation random numbers. `rng_state` must be seeded with `seed_random` before use.
+
+#define SAMPLING_PI radians(180)
+
+uint rng_state;
+
+uint hash(uint x) {
+    // PCG hash
+    uint state = x * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Every pass draws from its own stream, so that the random numbers of a pixel in one pass are
+// not correlated with the ones in another.
+#define RANDOM_STREAM_CAMERA 0u
+#define RANDOM_STREAM_LIGHTNING 1u
+#define RANDOM_STREAM_AMBIENT_OCCLUSION 2u
+
+// `seed` must be unique for the pixel and the sub-frame.
+void seed_random(uint seed, uint stream) {
+    rng_state = hash(seed ^ hash(stream));
+}
+
+float random() {
+    rng_state = hash(rng_state);
+    return float(rng_state) / 4294967296.0;
+}
+
+// Jittered sample in the `i`-th of `count` cells covering the unit square. The square is split
+// into `floor(sqrt(count))` rows of equal width cells, the last row takes the leftover samples.
+vec2 stratified_sample(uint i, uint count) {
+    uint rows = max(uint(sqrt(float(count))), 1u);
+    uint columns = max(count / rows, 1u);
+    uint row = min(i / columns, rows - 1u);
+    uint column = i - row * columns;
+    uint row_columns = row == rows - 1u ? max(count - row * columns, 1u) : columns;
+    return vec2(
+        (float(column) + random()) / float(row_columns),
+        (float(row) + random()) / float(rows)
+    );
+}
+
+void orthonormal_basis(vec3 n, out vec3 t, out vec3 b) {
+    vec3 up = abs(n.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    t = normalize(cross(up, n));
+    b = cross(n, t);
+}
+
+// Maps the unit square to the unit disk preserving stratification.
+vec2 concentric_disk(vec2 u) {
+    vec2 offset = 2.0 * u - 1.0;
+    if (offset.x == 0.0 && offset.y == 0.0) {
+        return vec2(0.0);
+    }
+    float r;
+    float theta;
+    if (abs(offset.x) > abs(offset.y)) {
+        r = offset.x;
+        theta = SAMPLING_PI / 4.0 * (offset.y / offset.x);
+    }
+    else {
+        r = offset.y;
+        theta = SAMPLING_PI / 2.0 - SAMPLING_PI / 4.0 * (offset.x / offset.y);
+    }
+    return r * vec2(cos(theta), sin(theta));
+}
+
+// Uniform direction inside the cone around `direction` with the half angle of `acos(cos_max)`.
+vec3 sample_cone(vec3 direction, float cos_max, vec2 u) {
+    float cos_theta = mix(1.0, cos_max, u.x);
+    float sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    float phi = 2.0 * SAMPLING_PI * u.y;
+
+    vec3 t;
+    vec3 b;
+    orthonormal_basis(direction, t, b);
+    return normalize(t * cos(phi) * sin_theta + b * sin(phi) * sin_theta + direction * cos_theta);
+}
+
+// Direction in the hemisphere around `normal` with the density proportional to the cosine.
+vec3 sample_cosine_hemisphere(vec3 normal, vec2 u) {
+    vec2 disk = concentric_disk(u);
+    float z = sqrt(max(1.0 - dot(disk, disk), 0.0));
+
+    vec3 t;
+    vec3 b;
+    orthonormal_basis(normal, t, b);
+    return normalize(t * disk.x + b * disk.y + normal * z);
+}
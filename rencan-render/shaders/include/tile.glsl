@@ -0,0 +1,19 @@
+// Must be included after the `Info` uniform with `screen`.
+
+// Must match rencan_core::tile::LOCAL_SIZE
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(push_constant) uniform Tile {
+    uvec2 tile_offset;
+};
+
+// Computes the pixel of the current invocation. Returns false if the pixel is out of the screen.
+bool pixel_of_invocation(out ivec2 pos, out uint idx) {
+    uvec2 pixel = tile_offset + gl_GlobalInvocationID.xy;
+    if (pixel.x >= screen.x || pixel.y >= screen.y) {
+        return false;
+    }
+    pos = ivec2(pixel);
+    idx = pixel.y * screen.x + pixel.x;
+    return true;
+}
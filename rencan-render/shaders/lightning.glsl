@@ -4,8 +4,6 @@
 
 #include "include/defs.glsl"
 
-layout(local_size_x_id = 0, local_size_y = 1, local_size_z = 1) in;
-
 // set0 is global for app
 layout(set = 0, binding = 0) readonly uniform Info {
     uvec2 screen;
@@ -14,6 +12,12 @@ layout(std140, set = 0, binding = 1) readonly uniform Camera {
     vec3 pos;
     mat3 rotation;
     float fov;
+    vec3 end_pos;
+    vec4 orientation;
+    vec4 end_orientation;
+    // Sub-frame of a motion blurred frame, seeds its own random numbers.
+    uint shutter_sample;
+    uint shutter_samples;
 };
 layout(std140, set = 0, binding = 2) readonly buffer PrimaryRays {
     Ray primary_rays[];
@@ -22,6 +26,9 @@ layout(std140, set = 0, binding = 3) readonly buffer PrimaryIntersections {
     Intersection primary_rays_intersections[];
 };
 layout(set = 0, binding = 4, rgba8) writeonly uniform image2D resultImage;
+layout(std430, set = 0, binding = 5) readonly buffer AmbientOcclusion {
+    float ambient_occlusion[];
+};
 
 // set1 for models
 layout(std140, set = 1, binding = 0) readonly uniform SceneInfo {
@@ -39,42 +46,83 @@ layout(std140, set = 1, binding = 3) readonly buffer Indexes {
 layout(std140, set = 1, binding = 4) readonly buffer HitBoxes {
     HitBoxRectangle[] hit_boxes;
 };
+layout(std140, set = 1, binding = 5) readonly buffer Primitives {
+    Primitive[] primitives;
+};
+layout(set = 1, binding = 6) readonly buffer Normals {
+    vec3[] normals;
+};
+layout(set = 1, binding = 7) readonly buffer Uvs {
+    vec2[] uvs;
+};
 
 // set2 for lights
-layout(std140, set = 2, binding = 0) readonly uniform DirectLightInfo {
-    DirectLight global_light;
+layout(std140, set = 2, binding = 0) readonly uniform DirectionLightsInfo {
+    uint direction_lights_count;
+};
+layout(std140, set = 2, binding = 1) readonly buffer DirectionLights {
+    DirectLight[] direction_lights;
 };
-layout(std140, set = 2, binding = 1) readonly uniform PointLightsInfo {
+layout(std140, set = 2, binding = 2) readonly uniform PointLightsInfo {
     uint point_lights_count;
 };
-layout(std140, set = 2, binding = 2) readonly buffer PointLights {
+layout(std140, set = 2, binding = 3) readonly buffer PointLights {
     PointLight[] point_lights;
 };
+layout(std140, set = 2, binding = 4) readonly uniform BackgroundInfo {
+    Background background;
+};
+layout(std140, set = 2, binding = 5) readonly buffer BackgroundPixels {
+    vec4[] background_pixels;
+};
+layout(std140, set = 2, binding = 6) readonly uniform EmittersInfo {
+    uint emitters_count;
+};
+layout(std140, set = 2, binding = 7) readonly buffer Emitters {
+    Emitter[] emitters;
+};
+layout(std140, set = 2, binding = 8) readonly uniform SpotLightsInfo {
+    uint spot_lights_count;
+};
+layout(std140, set = 2, binding = 9) readonly buffer SpotLights {
+    SpotLight[] spot_lights;
+};
+// Must match rencan_core::light::IES_TABLE_SIZE
+#define IES_TABLE_SIZE 64u
+layout(std430, set = 2, binding = 10) readonly buffer IesProfiles {
+    float[] ies_profiles;
+};
+layout(std140, set = 2, binding = 11) readonly uniform AmbientLightInfo {
+    vec3 ambient_light;
+};
+layout(std140, set = 2, binding = 12) readonly uniform MediumInfo {
+    Medium medium;
+};
+
+// set3 for the lightning settings
+layout(std140, set = 3, binding = 0) readonly uniform LightningInfo {
+    uint shadow_samples;
+};
 
+#include "include/tile.glsl"
+#include "include/quaternion.glsl"
 #include "include/ray_tracing.glsl"
+#include "include/background.glsl"
+#include "include/sampling.glsl"
 
 #define PI radians(180)
 
-vec3 compute_color_for_global_lights(
-    vec3 normal,
-    Intersection inter,
-    Intersection shadow_intersect,
-    ModelInfo model,
-    Ray primary_ray
-) {
-    mat4 isometry = model.isometry;
-    float albedo = model.albedo;
-
-    vec3 light_dir = -global_light.direction;
-
-    vec3 ray_direction = normalize(
-        (inverse(
-            mat3(isometry[0].xyz, isometry[1].xyz, isometry[2].xyz))) * primary_ray.direction.xyz
-    );
-
-    vec3 color = albedo / PI * global_light.intensity * global_light.color * max(dot(normal, -global_light.direction.xyz), 0.0);
+// Fraction of the light which passes `distance` through the medium. The medium ends at
+// `max_distance`, so lights at infinity are attenuated by it as well.
+vec3 medium_transmittance(float distance) {
+    if (medium.enabled == 0) {
+        return vec3(1.0);
+    }
+    return exp(-(medium.absorption + medium.scattering) * min(distance, medium.max_distance));
+}
 
-    return color;
+vec3 compute_color_for_direction_light(vec3 normal, DirectLight light, float albedo) {
+    return albedo / PI * light.intensity * light.color * max(dot(normal, -light.direction), 0.0);
 }
 
 vec3 compute_color_for_point_light(
@@ -90,57 +138,171 @@ vec3 compute_color_for_point_light(
     return intensity;
 }
 
-Ray make_shadow_ray_for_direction_light(Intersection inter, Ray previous) {
-    vec3 point = inter.point + inter.normal * 0.001;
+// Fraction of shadow rays stratified over the cone of the light which reach it.
+float direction_light_visibility(Intersection inter, DirectLight light) {
+    if (light.cast_shadows == 0) {
+        return 1.0;
+    }
+
+    vec3 point = offset_ray(inter.point, inter.normal);
+    vec3 light_dir = normalize(-light.direction);
+    float cos_max = cos(light.angular_diameter * 0.5);
+    uint samples = light.angular_diameter > 0.0 ? shadow_samples : 1u;
+
+    uint visible = 0;
+    for (uint i = 0; i < samples; i++) {
+        vec3 direction = sample_cone(light_dir, cos_max, stratified_sample(i, samples));
+        if (trace_any(Ray(point, vec4(direction, 0.0), 1.0 / 0.0, shutter_time), VISIBLE_SHADOW).is_intersect == 0) {
+            visible++;
+        }
+    }
+    return float(visible) / float(samples);
+}
+
+// Fraction of shadow rays stratified over the disk of the spherical light facing the point
+// which reach it.
+float sphere_light_visibility(Intersection inter, vec3 position, float radius) {
+    vec3 point = offset_ray(inter.point, inter.normal);
+    uint samples = radius > 0.0 ? shadow_samples : 1u;
+
+    vec3 t;
+    vec3 b;
+    orthonormal_basis(normalize(position - inter.point), t, b);
+
+    uint visible = 0;
+    for (uint i = 0; i < samples; i++) {
+        vec2 disk = radius * concentric_disk(stratified_sample(i, samples));
+        vec3 direction = position + t * disk.x + b * disk.y - point;
+        float distance = length(direction);
+        if (trace_any(Ray(point, vec4(direction / distance, 0.0), distance, shutter_time), VISIBLE_SHADOW).is_intersect == 0) {
+            visible++;
+        }
+    }
+    return float(visible) / float(samples);
+}
+
+float point_light_visibility(Intersection inter, PointLight light) {
+    return sphere_light_visibility(inter, light.position, light.radius);
+}
 
-    return Ray(point, vec4(-global_light.direction.xyz, 0.0), 1.0 / 0.0);
+// Relative intensity of the spot light in the direction from the light to the point.
+float spot_light_attenuation(SpotLight light, vec3 direction_from_light) {
+    float cos_angle = dot(direction_from_light, light.direction);
+    float attenuation = smoothstep(light.cos_outer, light.cos_inner, cos_angle);
+
+    if (light.profile_index != 0xFFFFFFFFu) {
+        float position = acos(clamp(cos_angle, -1.0, 1.0)) / PI * float(IES_TABLE_SIZE - 1u);
+        uint i = min(uint(position), IES_TABLE_SIZE - 2u);
+        uint offset = light.profile_index * IES_TABLE_SIZE;
+        attenuation *= mix(ies_profiles[offset + i], ies_profiles[offset + i + 1], position - float(i));
+    }
+
+    return attenuation;
+}
+
+vec3 compute_color_for_spot_light(Intersection inter, SpotLight light, float albedo) {
+    vec3 light_dir = light.position - inter.point;
+    float distance = length(light_dir);
+    light_dir = light_dir / distance;
+
+    float attenuation = spot_light_attenuation(light, -light_dir);
+    float cos_surface = max(dot(inter.normal, light_dir), 0.0);
+    if (attenuation == 0.0 || cos_surface == 0.0) {
+        return vec3(0.0);
+    }
+
+    float visibility = sphere_light_visibility(inter, light.position, light.radius);
+    vec3 intensity = light.intensity * light.color * attenuation / (distance * distance);
+    return visibility * medium_transmittance(distance) * albedo / PI * intensity * cos_surface;
+}
+
+// Picks an emitter with the probability proportional to its power.
+uint pick_emitter(float u) {
+    uint low = 0;
+    uint high = emitters_count - 1;
+    while (low < high) {
+        uint middle = (low + high) / 2;
+        if (emitters[middle].cdf < u) {
+            low = middle + 1;
+        }
+        else {
+            high = middle;
+        }
+    }
+    return low;
 }
 
-Ray make_shadow_ray_for_point_light(Intersection inter, Ray previous, PointLight light) {
-    vec3 direction_ray = light.position - inter.point;
+vec3 compute_color_for_emitters(Intersection inter, float albedo) {
+    if (emitters_count == 0) {
+        return vec3(0.0);
+    }
+
+    vec3 point = offset_ray(inter.point, inter.normal);
+    vec3 color = vec3(0.0);
+
+    for (uint i = 0; i < shadow_samples; i++) {
+        Emitter emitter = emitters[pick_emitter(random())];
+
+        // Uniform point on the triangle.
+        vec2 u = stratified_sample(i, shadow_samples);
+        float su = sqrt(u.x);
+        vec3 light_point = (1 - su) * emitter.v0 + su * (1 - u.y) * emitter.v1 + su * u.y * emitter.v2;
+        vec3 light_normal = normalize(cross(emitter.v1 - emitter.v0, emitter.v2 - emitter.v0));
+
+        vec3 direction = light_point - point;
+        float distance = length(direction);
+        direction = direction / distance;
 
-    vec3 point = inter.point + inter.normal * 0.001;
+        float cos_surface = dot(inter.normal, direction);
+        float cos_light = dot(light_normal, -direction);
+        if (cos_surface <= 0.0 || cos_light <= 0.0) {
+            continue;
+        }
+        // stops right before the emitter, which faces the point
+        float shadow_distance = length(offset_ray(light_point, light_normal) - point);
+        if (trace_any(Ray(point, vec4(direction, 0.0), shadow_distance, shutter_time), VISIBLE_SHADOW).is_intersect == 1) {
+            continue;
+        }
+
+        float pdf = emitter.probability / emitter.area;
+        color += medium_transmittance(distance) * emitter.radiance * albedo / PI * cos_surface *
+            cos_light / (distance * distance * pdf);
+    }
 
-    return Ray(point, vec4(normalize(direction_ray), 0.0), length(direction_ray));
+    return color / float(shadow_samples);
 }
 
-vec3 compute_color_diffuse_material(ModelInfo model, Intersection inter, Ray primary_ray) {
+// `occlusion` is the visibility of the ambient light at the point.
+vec3 compute_color_diffuse_material(
+    ModelInfo model,
+    Intersection inter,
+    Ray primary_ray,
+    float occlusion
+) {
     vec3 normal = inter.normal;
-    Intersection global_light_inter = trace(
-        make_shadow_ray_for_direction_light(inter, primary_ray)
-    );
 
-    vec3 color;
+    vec3 color = model.albedo * ambient_light * occlusion;
 
-    if (global_light_inter.is_intersect == 0) {
-        color = compute_color_for_global_lights(
+    for (int i = 0; i < direction_lights_count; i++) {
+        DirectLight light = direction_lights[i];
+        float visibility = direction_light_visibility(inter, light);
+        color += visibility * medium_transmittance(1.0 / 0.0) * compute_color_for_direction_light(
             normal,
-            inter,
-            global_light_inter,
-            model,
-            primary_ray
+            light,
+            model.albedo
         );
     }
-    else {
-        color = vec3(0.0);
-    }
 
     for (int i = 0; i < point_lights_count; i++) {
         PointLight light = point_lights[i];
         vec3 light_dir = light.position - inter.point;
         float distance_to_light = length(light_dir);
 
-        Intersection shadow_intersection = trace(
-            make_shadow_ray_for_point_light(
-                inter,
-                primary_ray,
-                light
-            )
-        );
-        if (shadow_intersection.is_intersect == 1) {
+        float visibility = point_light_visibility(inter, light);
+        if (visibility == 0.0) {
             continue;
         }
-        color = color + compute_color_for_point_light(
+        color = color + visibility * medium_transmittance(distance_to_light) * compute_color_for_point_light(
             normal,
             light_dir,
             light,
@@ -149,17 +311,117 @@ vec3 compute_color_diffuse_material(ModelInfo model, Intersection inter, Ray pri
         );
     }
 
+    for (int i = 0; i < spot_lights_count; i++) {
+        color += compute_color_for_spot_light(inter, spot_lights[i], model.albedo);
+    }
+
+    color += compute_color_for_emitters(inter, model.albedo);
+
+    if (background.image_based_lighting == 1) {
+        // Irradiance is approximated by the background radiance in the direction of the normal.
+        color += model.albedo * background_color(normal);
+    }
+
     return color;
 }
 
+float henyey_greenstein(float cos_theta, float g) {
+    float denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    return (1.0 - g * g) / (4.0 * PI * denominator * sqrt(denominator));
+}
+
+// Light of the point lights, spot lights and direction lights scattered by the medium at
+// the point towards the direction opposite to `ray_direction`, attenuated by the medium on the
+// way from the lights. Emissive geometry is ignored.
+vec3 medium_in_scattering(vec3 point, vec3 ray_direction) {
+    vec3 light = vec3(0.0);
+
+    for (int i = 0; i < direction_lights_count; i++) {
+        DirectLight direction_light = direction_lights[i];
+        vec3 light_dir = normalize(-direction_light.direction);
+        if (
+            direction_light.cast_shadows == 1 &&
+            trace_any(Ray(point, vec4(light_dir, 0.0), 1.0 / 0.0, shutter_time), VISIBLE_SHADOW).is_intersect == 1
+        ) {
+            continue;
+        }
+        light += medium_transmittance(1.0 / 0.0) * direction_light.color * direction_light.intensity *
+            henyey_greenstein(dot(ray_direction, light_dir), medium.anisotropy);
+    }
+
+    for (int i = 0; i < point_lights_count; i++) {
+        PointLight point_light = point_lights[i];
+        vec3 light_dir = point_light.position - point;
+        float distance = length(light_dir);
+        light_dir = light_dir / distance;
+        if (trace_any(Ray(point, vec4(light_dir, 0.0), distance, shutter_time), VISIBLE_SHADOW).is_intersect == 1) {
+            continue;
+        }
+        light += medium_transmittance(distance) * point_light.color * point_light.intensity /
+            (4 * PI * distance * distance) *
+            henyey_greenstein(dot(ray_direction, light_dir), medium.anisotropy);
+    }
+
+    for (int i = 0; i < spot_lights_count; i++) {
+        SpotLight spot_light = spot_lights[i];
+        vec3 light_dir = spot_light.position - point;
+        float distance = length(light_dir);
+        light_dir = light_dir / distance;
+        float attenuation = spot_light_attenuation(spot_light, -light_dir);
+        if (
+            attenuation == 0.0 ||
+            trace_any(Ray(point, vec4(light_dir, 0.0), distance, shutter_time), VISIBLE_SHADOW).is_intersect == 1
+        ) {
+            continue;
+        }
+        light += medium_transmittance(distance) * spot_light.color * spot_light.intensity *
+            attenuation / (distance * distance) *
+            henyey_greenstein(dot(ray_direction, light_dir), medium.anisotropy);
+    }
+
+    return light;
+}
+
+// Attenuates `color` coming from `distance` along the ray and adds the light scattered
+// towards the ray origin on the way, single scattering only.
+vec3 apply_medium(vec3 color, Ray ray, float distance) {
+    if (medium.enabled == 0) {
+        return color;
+    }
+
+    // `max_distance` is finite, so are the steps of the rays which miss all geometry.
+    distance = min(distance, medium.max_distance);
+    vec3 extinction = medium.absorption + medium.scattering;
+    float step_length = distance / float(medium.steps);
+
+    vec3 in_scattered = vec3(0.0);
+    for (uint i = 0; i < medium.steps; i++) {
+        float t = (float(i) + random()) * step_length;
+        vec3 point = ray.origin + ray.direction.xyz * t;
+        in_scattered += exp(-extinction * t) * medium.scattering *
+            medium_in_scattering(point, ray.direction.xyz) * step_length;
+    }
+
+    return color * exp(-extinction * distance) + in_scattered;
+}
+
+// Emissive models glow only on the front faces.
+vec3 emitted_radiance(ModelInfo model, Intersection inter) {
+    return inter.front_face == 1u ? model.emission : vec3(0.0);
+}
+
 vec3 compute_color_for_reflect_ray(ModelInfo model, Ray reflect_ray) {
-    Intersection inter = trace(reflect_ray);
+    Intersection inter = trace(reflect_ray, VISIBLE_REFLECTION);
     if (inter.is_intersect == 0.0) {
-        return model.specularity * vec3(0.0, 0.7, 0.4);
+        vec3 background = background_color(reflect_ray.direction.xyz);
+        return model.specularity * apply_medium(background, reflect_ray, 1.0 / 0.0);
     }
     vec3 color = vec3(0.0);
     if (model.specularity > 0.01) {
-        color += model.specularity * compute_color_diffuse_material(models[inter.model_id], inter, reflect_ray);
+        ModelInfo reflected = models[inter.model_id];
+        vec3 reflected_color =
+            emitted_radiance(reflected, inter) + compute_color_diffuse_material(reflected, inter, reflect_ray, 1.0);
+        color += model.specularity * apply_medium(reflected_color, reflect_ray, inter.distance);
     }
 
     return color;
@@ -169,29 +431,36 @@ void lights(uint idx, Intersection inter, Ray primary_ray, ivec2 pos) {
     ModelInfo model = models[inter.model_id];
     uvec3 index = indexes[inter.triangle_idx];
 
-    vec3 color = compute_color_diffuse_material(model, inter, primary_ray);
+    vec3 color = compute_color_diffuse_material(model, inter, primary_ray, ambient_occlusion[idx]);
 
     vec3 next_direction = reflect(primary_ray.direction.xyz, inter.normal);
-    vec3 reflect_color = compute_color_for_reflect_ray(model, Ray(inter.point + inter.normal * 0.1, vec4(next_direction, 0.0), 1.0 / 0.0));
+    vec3 reflect_color = compute_color_for_reflect_ray(model, Ray(offset_ray(inter.point, inter.normal), vec4(next_direction, 0.0), 1.0 / 0.0, shutter_time));
 
-    color = color + reflect_color;
+    color = color + reflect_color + emitted_radiance(model, inter);
+    color = apply_medium(color, primary_ray, inter.distance);
 
     imageStore(resultImage, pos, vec4(color, 0.0));
 }
 
 void main() {
-    uint idx = gl_GlobalInvocationID.x;
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
 
     Intersection inter = primary_rays_intersections[idx];
     Ray primary_ray = primary_rays[idx];
 
-    ivec2 pos = ivec2(idx % screen.x, idx / screen.x);
+    seed_random(idx + shutter_sample * screen.x * screen.y, RANDOM_STREAM_LIGHTNING);
+    shutter_time = primary_ray.time;
 
     if (inter.is_intersect == 1) {
         lights(idx, inter, primary_ray, pos);
     }
     else if (inter.is_intersect == 0) {
-        imageStore(resultImage, pos, vec4(0.0, 0.7, 0.4, 0.0));
+        vec3 background = background_color(primary_ray.direction.xyz);
+        imageStore(resultImage, pos, vec4(apply_medium(background, primary_ray, 1.0 / 0.0), 0.0));
     }
     else {
         // unreachable
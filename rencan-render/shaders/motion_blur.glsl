@@ -0,0 +1,55 @@
+#version 450
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "include/defs.glsl"
+
+// set0 is global for app
+layout(set = 0, binding = 0) readonly uniform Info {
+    uvec2 screen;
+};
+layout(std140, set = 0, binding = 1) readonly uniform Camera {
+    vec3 pos;
+    mat3 rotation;
+    float fov;
+};
+layout(std140, set = 0, binding = 2) readonly buffer PrimaryRays {
+    Ray primary_rays[];
+};
+layout(std140, set = 0, binding = 3) readonly buffer PrimaryIntersections {
+    Intersection primary_rays_intersections[];
+};
+layout(set = 0, binding = 4, rgba8) uniform image2D resultImage;
+layout(std430, set = 0, binding = 5) readonly buffer AmbientOcclusion {
+    float ambient_occlusion[];
+};
+
+// set1 for the accumulation of sub-frames
+layout(std140, set = 1, binding = 0) readonly uniform ShutterInfo {
+    uint shutter_sample;
+    uint shutter_samples;
+};
+layout(std430, set = 1, binding = 1) buffer Accumulation {
+    vec4 accumulation[];
+};
+
+#include "include/tile.glsl"
+
+void main() {
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
+
+    vec4 color = imageLoad(resultImage, pos) / float(shutter_samples);
+    if (shutter_sample == 0) {
+        accumulation[idx] = color;
+    } else {
+        accumulation[idx] += color;
+    }
+
+    if (shutter_sample + 1 == shutter_samples) {
+        imageStore(resultImage, pos, accumulation[idx]);
+    }
+}
@@ -0,0 +1,58 @@
+#version 450
+
+#extension GL_GOOGLE_include_directive : require
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+#include "include/defs.glsl"
+
+// set0 for the query
+layout(std140, set = 0, binding = 0) readonly uniform QueryInfo {
+    uint rays_offset;
+    uint rays_count;
+    uint any_hit;
+};
+layout(std140, set = 0, binding = 1) readonly buffer Rays {
+    Ray rays[];
+};
+layout(std140, set = 0, binding = 2) writeonly buffer Intersections {
+    Intersection intersections[];
+};
+
+// set1 for models
+layout(std140, set = 1, binding = 0) readonly uniform SceneInfo {
+    uint model_counts;
+};
+layout(std140, set = 1, binding = 1) readonly buffer ModelInfos {
+    ModelInfo models[];
+};
+layout(set = 1, binding = 2) readonly buffer Vertices {
+    vec3[] vertices;
+};
+layout(std140, set = 1, binding = 3) readonly buffer Indexes {
+    uvec3[] indexes;
+};
+layout(std140, set = 1, binding = 4) readonly buffer HitBoxes {
+    HitBoxRectangle[] hit_boxes;
+};
+layout(std140, set = 1, binding = 5) readonly buffer Primitives {
+    Primitive[] primitives;
+};
+layout(set = 1, binding = 6) readonly buffer Normals {
+    vec3[] normals;
+};
+layout(set = 1, binding = 7) readonly buffer Uvs {
+    vec2[] uvs;
+};
+
+#include "include/quaternion.glsl"
+#include "include/ray_tracing.glsl"
+
+void main() {
+    uint idx = rays_offset + gl_GlobalInvocationID.x;
+    if (idx >= rays_count) {
+        return;
+    }
+
+    intersections[idx] = _trace(rays[idx], any_hit == 1, VISIBLE_ALL);
+}
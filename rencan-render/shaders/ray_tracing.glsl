@@ -2,8 +2,6 @@
 
 #extension GL_GOOGLE_include_directive : require
 
-layout(local_size_x_id = 0, local_size_y = 1, local_size_z = 1) in;
-
 #include "include/defs.glsl"
 
 layout(set = 0, binding = 0) readonly uniform Info {
@@ -37,14 +35,29 @@ layout(std140, set = 1, binding = 3) readonly buffer Indexes {
 layout(std140, set = 1, binding = 4) readonly buffer HitBoxes {
     HitBoxRectangle[] hit_boxes;
 };
+layout(std140, set = 1, binding = 5) readonly buffer Primitives {
+    Primitive[] primitives;
+};
+layout(set = 1, binding = 6) readonly buffer Normals {
+    vec3[] normals;
+};
+layout(set = 1, binding = 7) readonly buffer Uvs {
+    vec2[] uvs;
+};
 
+#include "include/tile.glsl"
+#include "include/quaternion.glsl"
 #include "include/ray_tracing.glsl"
 
 void main() {
-    uint idx = gl_GlobalInvocationID.x;
+    ivec2 pos;
+    uint idx;
+    if (!pixel_of_invocation(pos, idx)) {
+        return;
+    }
 
     Ray ray = rays[idx];
-    Intersection inter = trace(ray);
+    Intersection inter = trace(ray, VISIBLE_CAMERA);
 
     intersections[idx] = inter;
 }
\ No newline at end of file
@@ -1,8 +1,10 @@
-use crate::commands;
+use crate::commands::{self, DenoiseSettings};
 use rencan_core::app::AppBuilder;
 
 pub trait AppBuilderRtExt: Sized {
     fn then_ray_tracing_pipeline(self) -> Self;
+    /// Filters the noise of the output image, must be added after the lightning.
+    fn then_denoise(self, settings: DenoiseSettings) -> Self;
 }
 
 impl AppBuilderRtExt for AppBuilder {
@@ -11,4 +13,8 @@ impl AppBuilderRtExt for AppBuilder {
         self.then_command(Box::new(commands::ComputeRaysCommandFactory::new(device.clone())))
             .then_command(Box::new(commands::RayTraceCommandFactory::new(device)))
     }
+    fn then_denoise(self, settings: DenoiseSettings) -> Self {
+        let device = self.info().device.clone();
+        self.then_command(Box::new(commands::DenoiseCommandFactory::new(device, settings)))
+    }
 }
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferUsage, CpuBufferPool},
+    command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout,
+        PipelineLayoutAbstract,
+    },
+    device::Device,
+    pipeline::ComputePipeline,
+};
+
+use crate::core::{CommandFactory, CommandFactoryContext};
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/ambient_occlusion.glsl"
+    }
+}
+
+/// Traces cosine-weighted hemisphere rays from every primary hit and writes the fraction of
+/// unoccluded ones to `Buffers::ambient_occlusion`, which the lightning multiplies the ambient
+/// light by. Must run after the ray tracing.
+pub struct AmbientOcclusionCommandFactory {
+    pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
+    infos: CpuBufferPool<cs::ty::AmbientOcclusionInfo>,
+    samples: u32,
+    max_distance: f32,
+}
+
+impl AmbientOcclusionCommandFactory {
+    pub fn new(device: Arc<Device>) -> Self {
+        let shader = cs::Shader::load(device.clone()).unwrap();
+        let pipeline = Arc::new(
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
+        );
+        AmbientOcclusionCommandFactory {
+            pipeline,
+            infos: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+            samples: 16,
+            max_distance: 1.0,
+        }
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Occluders further than this distance are ignored.
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+}
+
+impl CommandFactory for AmbientOcclusionCommandFactory {
+    fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>) {
+        let CommandFactoryContext { app_info, buffers, .. } = ctx;
+
+        let layout_2 = self.pipeline.layout().descriptor_set_layout(2).unwrap();
+        let info = cs::ty::AmbientOcclusionInfo {
+            samples: self.samples,
+            max_distance: self.max_distance,
+        };
+        let set_2 = Arc::new(
+            PersistentDescriptorSet::start(layout_2.clone())
+                .add_buffer(self.infos.next(info).unwrap())
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        for tile in app_info.tiles() {
+            let mut command = AutoCommandBufferBuilder::new(
+                app_info.device.clone(),
+                app_info.graphics_queue.family(),
+            )
+            .unwrap();
+
+            command
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    (buffers.global_app_set.clone(), buffers.models_set.clone(), set_2.clone()),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
+                .unwrap();
+
+            commands.push(command.build().unwrap());
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ambient_occlusion"
+    }
+}
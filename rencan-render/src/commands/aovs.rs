@@ -0,0 +1,185 @@
+use std::sync::{Arc, Mutex};
+
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer, CpuBufferPool},
+    command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout,
+        PipelineLayoutAbstract,
+    },
+    device::{Device, Queue},
+    format::Format,
+    image::{Dimensions, ImageUsage, StorageImage},
+    pipeline::ComputePipeline,
+    sync::GpuFuture,
+};
+
+use crate::core::{aov::Aov, AppInfo, CommandFactory, CommandFactoryContext, Screen};
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/aovs.glsl"
+    }
+}
+
+/// Downloaded AOV image. Pixels are stored row by row.
+#[derive(Debug, Clone)]
+pub struct AovImage {
+    pub aov: Aov,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+/// Images written by [`AovCommandFactory`]. It is shared between the factory and the user, so
+/// AOVs can be exported after the factory is moved into the app.
+pub struct AovOutputs {
+    aovs: Vec<Aov>,
+    images: Mutex<Option<(Screen, Vec<Arc<StorageImage<Format>>>)>>,
+}
+
+impl AovOutputs {
+    fn new(aovs: Vec<Aov>) -> Self {
+        AovOutputs { aovs, images: Mutex::new(None) }
+    }
+
+    pub fn aovs(&self) -> &[Aov] {
+        &self.aovs
+    }
+
+    /// Image of the AOV from the last rendered frame.
+    pub fn image(&self, aov: Aov) -> Option<Arc<StorageImage<Format>>> {
+        let position = self.aovs.iter().position(|a| *a == aov)?;
+        let images = self.images.lock().unwrap();
+        images.as_ref().map(|(_, images)| images[position].clone())
+    }
+
+    /// Copies the AOV image to the host. Blocks until the copy is finished, so the frame must be
+    /// already submitted.
+    pub fn download(&self, queue: &Arc<Queue>, aov: Aov) -> Option<AovImage> {
+        let image = self.image(aov)?;
+        let device = queue.device().clone();
+        let [width, height] = match image.dimensions() {
+            Dimensions::Dim2d { width, height } => [width, height],
+            _ => unreachable!(),
+        };
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage { transfer_destination: true, ..BufferUsage::none() },
+            false,
+            (0..width * height).map(|_| [0.0f32; 4]),
+        )
+        .unwrap();
+
+        let mut command = AutoCommandBufferBuilder::new(device, queue.family()).unwrap();
+        command.copy_image_to_buffer(image, buffer.clone()).unwrap();
+        command
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let pixels = buffer.read().unwrap().to_vec();
+        Some(AovImage { aov, width, height, pixels })
+    }
+
+    fn images(&self, app_info: &AppInfo) -> Vec<Arc<StorageImage<Format>>> {
+        let mut images = self.images.lock().unwrap();
+        if let Some((screen, images)) = images.as_ref() {
+            if *screen == app_info.screen {
+                return images.clone();
+            }
+        }
+        let new_images = self.aovs.iter().map(|_| make_image(app_info)).collect::<Vec<_>>();
+        *images = Some((app_info.screen.clone(), new_images.clone()));
+        new_images
+    }
+}
+
+/// Writes requested AOVs of primary intersections.
+pub struct AovCommandFactory {
+    pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
+    infos: CpuBufferPool<cs::ty::AovInfo>,
+    outputs: Arc<AovOutputs>,
+}
+
+impl AovCommandFactory {
+    pub fn new(device: Arc<Device>, aovs: &[Aov]) -> Self {
+        let shader = cs::Shader::load(device.clone()).unwrap();
+        let pipeline = Arc::new(
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
+        );
+        AovCommandFactory {
+            pipeline,
+            infos: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+            outputs: Arc::new(AovOutputs::new(aovs.to_vec())),
+        }
+    }
+
+    pub fn outputs(&self) -> Arc<AovOutputs> {
+        self.outputs.clone()
+    }
+}
+
+impl CommandFactory for AovCommandFactory {
+    fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>) {
+        let CommandFactoryContext { app_info, buffers, .. } = ctx;
+        let images = self.outputs.images(app_info);
+
+        let layout_2 = self.pipeline.layout().descriptor_set_layout(2).unwrap();
+
+        for (aov, image) in self.outputs.aovs.iter().zip(images.into_iter()) {
+            let set_2 = Arc::new(
+                PersistentDescriptorSet::start(layout_2.clone())
+                    .add_buffer(
+                        self.infos.next(cs::ty::AovInfo { aov: aov.into_uniform() }).unwrap(),
+                    )
+                    .unwrap()
+                    .add_image(image)
+                    .unwrap()
+                    .build()
+                    .unwrap(),
+            );
+
+            for tile in app_info.tiles() {
+                let mut command = AutoCommandBufferBuilder::new(
+                    app_info.device.clone(),
+                    app_info.graphics_queue.family(),
+                )
+                .unwrap();
+
+                command
+                    .dispatch(
+                        tile.workgroups(),
+                        self.pipeline.clone(),
+                        (buffers.global_app_set.clone(), buffers.models_set.clone(), set_2.clone()),
+                        cs::ty::Tile { tile_offset: tile.offset },
+                    )
+                    .unwrap();
+
+                commands.push(command.build().unwrap());
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "aovs"
+    }
+}
+
+fn make_image(app_info: &AppInfo) -> Arc<StorageImage<Format>> {
+    StorageImage::with_usage(
+        app_info.device.clone(),
+        Dimensions::Dim2d { width: app_info.screen.width(), height: app_info.screen.height() },
+        Format::R32G32B32A32Sfloat,
+        ImageUsage { storage: true, transfer_source: true, ..ImageUsage::none() },
+        std::iter::once(app_info.graphics_queue.family()),
+    )
+    .unwrap()
+}
@@ -46,16 +46,27 @@ impl CommandFactory for CheckBoardCommandFactory {
         let set_0 = buffers.global_app_set.clone();
         let set_1 = buffers.models_set.clone();
 
-        let mut command =
-            AutoCommandBufferBuilder::new(device.clone(), app_info.graphics_queue.family())
+        for tile in app_info.tiles() {
+            let mut command =
+                AutoCommandBufferBuilder::new(device.clone(), app_info.graphics_queue.family())
+                    .unwrap();
+
+            command
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    (set_0.clone(), set_1.clone()),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
                 .unwrap();
 
-        command
-            .dispatch([ctx.app_info.size_of_image_array() as u32 / 64, 1, 1], self.pipeline.clone(), (set_0, set_1), ())
-            .unwrap();
+            let command = command.build().unwrap();
 
-        let command = command.build().unwrap();
+            commands.push(command);
+        }
+    }
 
-        commands.push(command);
+    fn name(&self) -> &'static str {
+        "checkboard"
     }
 }
@@ -11,7 +11,10 @@ use vulkano::{
 
 use rencan_core::CommandFactory;
 
-use crate::core::{camera::Camera, CommandFactoryContext, Screen};
+use crate::core::{
+    camera::{Camera, Shutter},
+    CommandFactoryContext, Screen,
+};
 use nalgebra::Point3;
 use std::cell::RefCell;
 
@@ -26,19 +29,14 @@ pub struct ComputeRaysCommandFactory {
     pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
     prev_camera: RefCell<Camera>,
     prev_screen: RefCell<Screen>,
-    local_size_x: u32,
+    prev_shutter: RefCell<Option<Shutter>>,
 }
 
 impl ComputeRaysCommandFactory {
     pub fn new(device: Arc<Device>) -> Self {
         let shader = cs::Shader::load(device.clone()).unwrap();
-        let local_size_x = device.physical_device().extended_properties().subgroup_size().unwrap_or(32);
-
-        let constants = cs::SpecializationConstants {
-            constant_0: local_size_x,
-        };
         let pipeline = Arc::new(
-            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &constants, None).unwrap(),
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
         );
         ComputeRaysCommandFactory {
             pipeline,
@@ -48,7 +46,7 @@ impl ComputeRaysCommandFactory {
                 0.0,
             )),
             prev_screen: RefCell::new(Screen::new(0, 0)),
-            local_size_x
+            prev_shutter: RefCell::new(None),
         }
     }
 }
@@ -61,27 +59,40 @@ impl CommandFactory for ComputeRaysCommandFactory {
     ) {
         if *self.prev_screen.borrow() == ctx.app_info.screen
             && *self.prev_camera.borrow() == *ctx.camera
+            && *self.prev_shutter.borrow() == Some(ctx.shutter)
         {
             return;
         }
 
         *self.prev_camera.borrow_mut() = ctx.camera.clone();
         *self.prev_screen.borrow_mut() = ctx.app_info.screen.clone();
-
-        let mut calc_rays = AutoCommandBufferBuilder::new(
-            ctx.app_info.device.clone(),
-            ctx.app_info.graphics_queue.family(),
-        )
-        .unwrap();
+        *self.prev_shutter.borrow_mut() = Some(ctx.shutter);
 
         let set_0 = ctx.buffers.global_app_set.clone();
 
-        calc_rays
-            .dispatch([ctx.app_info.size_of_image_array() as u32 / self.local_size_x, 1, 1], self.pipeline.clone(), set_0, ())
+        for tile in ctx.app_info.tiles() {
+            let mut calc_rays = AutoCommandBufferBuilder::new(
+                ctx.app_info.device.clone(),
+                ctx.app_info.graphics_queue.family(),
+            )
             .unwrap();
 
-        let calc_rays_command = calc_rays.build().unwrap();
+            calc_rays
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    set_0.clone(),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
+                .unwrap();
+
+            let calc_rays_command = calc_rays.build().unwrap();
+
+            commands.push(calc_rays_command);
+        }
+    }
 
-        commands.push(calc_rays_command);
+    fn name(&self) -> &'static str {
+        "compute_rays"
     }
 }
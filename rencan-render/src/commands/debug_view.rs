@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferUsage, CpuBufferPool},
+    command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout,
+        PipelineLayoutAbstract,
+    },
+    device::Device,
+    pipeline::ComputePipeline,
+};
+
+use crate::core::{debug_view::DebugView, CommandFactory, CommandFactoryContext};
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/debug_view.glsl"
+    }
+}
+
+/// Writes the [`DebugView`] selected in the app instead of the lightning. Does nothing for
+/// [`DebugView::Lit`].
+pub struct DebugViewCommandFactory {
+    pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
+    infos: CpuBufferPool<cs::ty::DebugInfo>,
+    checkerboard_scale: f32,
+}
+
+impl DebugViewCommandFactory {
+    pub fn new(device: Arc<Device>) -> Self {
+        let shader = cs::Shader::load(device.clone()).unwrap();
+        let pipeline = Arc::new(
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
+        );
+        DebugViewCommandFactory {
+            pipeline,
+            infos: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+            checkerboard_scale: 0.1,
+        }
+    }
+
+    pub fn with_checkerboard_scale(mut self, scale: f32) -> Self {
+        self.checkerboard_scale = scale;
+        self
+    }
+}
+
+impl CommandFactory for DebugViewCommandFactory {
+    fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>) {
+        if ctx.debug_view == DebugView::Lit {
+            return;
+        }
+
+        let CommandFactoryContext { app_info, buffers, .. } = ctx;
+
+        let layout_3 = self.pipeline.layout().descriptor_set_layout(3).unwrap();
+        let info = cs::ty::DebugInfo {
+            view: ctx.debug_view.into_uniform(),
+            checkerboard_scale: self.checkerboard_scale,
+        };
+        let set_3 = Arc::new(
+            PersistentDescriptorSet::start(layout_3.clone())
+                .add_buffer(self.infos.next(info).unwrap())
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        for tile in app_info.tiles() {
+            let mut command = AutoCommandBufferBuilder::new(
+                app_info.device.clone(),
+                app_info.graphics_queue.family(),
+            )
+            .unwrap();
+
+            command
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    (
+                        buffers.global_app_set.clone(),
+                        buffers.models_set.clone(),
+                        buffers.lights_set.clone(),
+                        set_3.clone(),
+                    ),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
+                .unwrap();
+
+            commands.push(command.build().unwrap());
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "debug_view"
+    }
+}
@@ -0,0 +1,181 @@
+use std::{cell::RefCell, sync::Arc};
+
+use vulkano::{
+    buffer::{BufferUsage, CpuBufferPool},
+    command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout,
+        PipelineLayoutAbstract,
+    },
+    device::Device,
+    format::Format,
+    image::{Dimensions, ImageUsage, ImageViewAccess, StorageImage},
+    pipeline::ComputePipeline,
+};
+
+use crate::core::{debug_view::DebugView, AppInfo, CommandFactory, CommandFactoryContext, Screen};
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/denoise.glsl"
+    }
+}
+
+/// Settings of the edge-avoiding à-trous wavelet filter.
+#[derive(Debug, Clone)]
+pub struct DenoiseSettings {
+    /// Count of filter passes. Every pass doubles the distance between filter taps.
+    pub iterations: u32,
+    /// Edge-stopping weight for the difference of colors. It is halved every iteration.
+    pub sigma_color: f32,
+    /// Exponent of the dot product of normals.
+    pub sigma_normal: f32,
+    /// Edge-stopping weight for the difference of depths.
+    pub sigma_depth: f32,
+    /// Edge-stopping weight for the difference of albedos.
+    pub sigma_albedo: f32,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        DenoiseSettings {
+            iterations: 5,
+            sigma_color: 0.6,
+            sigma_normal: 128.0,
+            sigma_depth: 0.5,
+            sigma_albedo: 0.1,
+        }
+    }
+}
+
+pub struct DenoiseCommandFactory {
+    pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
+    settings: DenoiseSettings,
+    params: CpuBufferPool<cs::ty::Params>,
+    images: RefCell<Option<(Screen, [Arc<StorageImage<Format>>; 2])>>,
+}
+
+impl DenoiseCommandFactory {
+    pub fn new(device: Arc<Device>, settings: DenoiseSettings) -> Self {
+        let shader = cs::Shader::load(device.clone()).unwrap();
+        let pipeline = Arc::new(
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
+        );
+        DenoiseCommandFactory {
+            pipeline,
+            settings,
+            params: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+            images: RefCell::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> &DenoiseSettings {
+        &self.settings
+    }
+
+    fn images(&self, app_info: &AppInfo) -> [Arc<StorageImage<Format>>; 2] {
+        let mut images = self.images.borrow_mut();
+        if let Some((screen, images)) = images.as_ref() {
+            if *screen == app_info.screen {
+                return images.clone();
+            }
+        }
+        let new_images = [make_image(app_info), make_image(app_info)];
+        *images = Some((app_info.screen.clone(), new_images.clone()));
+        new_images
+    }
+
+    fn add_pass(
+        &self,
+        ctx: &CommandFactoryContext,
+        params: cs::ty::Params,
+        source: Arc<dyn ImageViewAccess + Send + Sync>,
+        target: Arc<dyn ImageViewAccess + Send + Sync>,
+        commands: &mut Vec<AutoCommandBuffer>,
+    ) {
+        let CommandFactoryContext { app_info, buffers, .. } = ctx;
+
+        let layout_2 = self.pipeline.layout().descriptor_set_layout(2).unwrap();
+        let set_2 = Arc::new(
+            PersistentDescriptorSet::start(layout_2.clone())
+                .add_buffer(self.params.next(params).unwrap())
+                .unwrap()
+                .add_image(source)
+                .unwrap()
+                .add_image(target)
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        for tile in app_info.tiles() {
+            let mut command = AutoCommandBufferBuilder::new(
+                app_info.device.clone(),
+                app_info.graphics_queue.family(),
+            )
+            .unwrap();
+
+            command
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    (buffers.global_app_set.clone(), buffers.models_set.clone(), set_2.clone()),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
+                .unwrap();
+
+            commands.push(command.build().unwrap());
+        }
+    }
+}
+
+impl CommandFactory for DenoiseCommandFactory {
+    fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>) {
+        // Debug views show exact per-pixel values, filtering would smear them.
+        if self.settings.iterations == 0 || ctx.debug_view != DebugView::Lit {
+            return;
+        }
+
+        let images = self.images(ctx.app_info);
+        let output: Arc<dyn ImageViewAccess + Send + Sync> = ctx.buffers.output_image.clone();
+
+        let mut source = output.clone();
+        for i in 0..self.settings.iterations {
+            let target: Arc<dyn ImageViewAccess + Send + Sync> = images[i as usize % 2].clone();
+            let params = cs::ty::Params {
+                step_width: 1 << i,
+                sigma_color: self.settings.sigma_color / (1 << i) as f32,
+                sigma_normal: self.settings.sigma_normal,
+                sigma_depth: self.settings.sigma_depth,
+                sigma_albedo: self.settings.sigma_albedo,
+            };
+            self.add_pass(&ctx, params, source, target.clone(), commands);
+            source = target;
+        }
+
+        let copy = cs::ty::Params {
+            step_width: 0,
+            sigma_color: 0.0,
+            sigma_normal: 0.0,
+            sigma_depth: 0.0,
+            sigma_albedo: 0.0,
+        };
+        self.add_pass(&ctx, copy, source, output, commands);
+    }
+
+    fn name(&self) -> &'static str {
+        "denoise"
+    }
+}
+
+fn make_image(app_info: &AppInfo) -> Arc<StorageImage<Format>> {
+    StorageImage::with_usage(
+        app_info.device.clone(),
+        Dimensions::Dim2d { width: app_info.screen.width(), height: app_info.screen.height() },
+        Format::R8G8B8A8Unorm,
+        ImageUsage { storage: true, ..ImageUsage::none() },
+        std::iter::once(app_info.graphics_queue.family()),
+    )
+    .unwrap()
+}
@@ -1,13 +1,20 @@
 use std::sync::Arc;
 
 use vulkano::{
+    buffer::{BufferUsage, CpuBufferPool},
     command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder},
-    descriptor::pipeline_layout::PipelineLayout,
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout, DescriptorSet,
+        PipelineLayoutAbstract,
+    },
     device::Device,
     pipeline::ComputePipeline,
 };
 
-use crate::core::{CommandFactory, CommandFactoryContext};
+use crate::{
+    commands::DebugViewCommandFactory,
+    core::{debug_view::DebugView, tile::Tile, CommandFactory, CommandFactoryContext},
+};
 
 pub mod lightning_cs {
     vulkano_shaders::shader! {
@@ -18,49 +25,80 @@ pub mod lightning_cs {
 
 pub struct LightningCommandFactory {
     lightning_pipeline: Arc<ComputePipeline<PipelineLayout<lightning_cs::Layout>>>,
-    local_size_x: u32,
+    debug_view: DebugViewCommandFactory,
+    infos: CpuBufferPool<lightning_cs::ty::LightningInfo>,
+    shadow_samples: u32,
 }
 
 impl LightningCommandFactory {
     pub fn new(device: Arc<Device>) -> Self {
-        let local_size_x = device.physical_device().extended_properties().subgroup_size().unwrap_or(32);
-
-        let constants = lightning_cs::SpecializationConstants {
-            constant_0: local_size_x,
-        };
-
         let lightning_pipeline = Arc::new(
             ComputePipeline::new(
                 device.clone(),
-                &lightning_cs::Shader::load(device).unwrap().main_entry_point(),
-                &constants,
+                &lightning_cs::Shader::load(device.clone()).unwrap().main_entry_point(),
+                &(),
                 None,
             )
             .unwrap(),
         );
-        LightningCommandFactory { lightning_pipeline, local_size_x }
+        LightningCommandFactory {
+            lightning_pipeline,
+            debug_view: DebugViewCommandFactory::new(device.clone()),
+            infos: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+            shadow_samples: 16,
+        }
+    }
+
+    /// Count of shadow rays per light with a radius or an angular diameter and for the emissive
+    /// geometry. Lights without the size always use one ray.
+    pub fn with_shadow_samples(mut self, shadow_samples: u32) -> Self {
+        self.shadow_samples = shadow_samples.max(1);
+        self
     }
 }
 
 impl CommandFactory for LightningCommandFactory {
     fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>) {
-        let mut command = AutoCommandBufferBuilder::new(
-            ctx.app_info.device.clone(),
-            ctx.app_info.graphics_queue.family(),
-        )
-        .unwrap();
+        if ctx.debug_view != DebugView::Lit {
+            self.debug_view.make_command(ctx, commands);
+            return;
+        }
 
-        add_lightning(self, &ctx, &mut command);
+        let layout_3 = self.lightning_pipeline.layout().descriptor_set_layout(3).unwrap();
+        let info = lightning_cs::ty::LightningInfo { shadow_samples: self.shadow_samples };
+        let set_3: Arc<dyn DescriptorSet + Send + Sync> = Arc::new(
+            PersistentDescriptorSet::start(layout_3.clone())
+                .add_buffer(self.infos.next(info).unwrap())
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        for tile in ctx.app_info.tiles() {
+            let mut command = AutoCommandBufferBuilder::new(
+                ctx.app_info.device.clone(),
+                ctx.app_info.graphics_queue.family(),
+            )
+            .unwrap();
 
-        let command = command.build().unwrap();
+            add_lightning(self, &ctx, &tile, set_3.clone(), &mut command);
+
+            let command = command.build().unwrap();
+
+            commands.push(command)
+        }
+    }
 
-        commands.push(command)
+    fn name(&self) -> &'static str {
+        "lightning"
     }
 }
 
 fn add_lightning(
     factory: &LightningCommandFactory,
     ctx: &CommandFactoryContext,
+    tile: &Tile,
+    set_3: Arc<dyn DescriptorSet + Send + Sync>,
     command: &mut AutoCommandBufferBuilder,
 ) {
     let CommandFactoryContext { buffers, .. } = ctx;
@@ -71,10 +109,10 @@ fn add_lightning(
 
     command
         .dispatch(
-            [ctx.app_info.size_of_image_array() as u32 / factory.local_size_x, 1, 1],
+            tile.workgroups(),
             factory.lightning_pipeline.clone(),
-            (set_0, set_1, set_2),
-            (),
+            (set_0, set_1, set_2, set_3),
+            lightning_cs::ty::Tile { tile_offset: tile.offset },
         )
         .unwrap();
 }
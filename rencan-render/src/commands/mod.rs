@@ -1,13 +1,21 @@
+mod ambient_occlusion;
+mod aovs;
 mod checkboard_pattern;
 mod compute_rays;
-//mod facing_ratio;
+mod debug_view;
+mod denoise;
 mod lightning;
+mod motion_blur;
 mod ray_trace;
 
+pub use ambient_occlusion::AmbientOcclusionCommandFactory;
+pub use aovs::{AovCommandFactory, AovImage, AovOutputs};
 pub use checkboard_pattern::CheckBoardCommandFactory;
 pub use compute_rays::ComputeRaysCommandFactory;
-//pub use facing_ratio::FacingRatioCommandFactory;
+pub use debug_view::DebugViewCommandFactory;
+pub use denoise::{DenoiseCommandFactory, DenoiseSettings};
 pub use lightning::LightningCommandFactory;
+pub use motion_blur::MotionBlurCommandFactory;
 pub use ray_trace::RayTraceCommandFactory;
 
 pub mod shaders {
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferUsage, CpuBufferPool},
+    command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder},
+    descriptor::{
+        descriptor_set::PersistentDescriptorSet, pipeline_layout::PipelineLayout,
+        PipelineLayoutAbstract,
+    },
+    device::Device,
+    pipeline::ComputePipeline,
+};
+
+use crate::core::{CommandFactory, CommandFactoryContext};
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "shaders/motion_blur.glsl"
+    }
+}
+
+/// Sums the sub-frames of a motion blurred frame in `Buffers::accumulation` and writes their
+/// average to the output image after the last one. Does nothing when the frame has only one
+/// sub-frame. Must run after all passes which write the output image.
+pub struct MotionBlurCommandFactory {
+    pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
+    infos: CpuBufferPool<cs::ty::ShutterInfo>,
+}
+
+impl MotionBlurCommandFactory {
+    pub fn new(device: Arc<Device>) -> Self {
+        let shader = cs::Shader::load(device.clone()).unwrap();
+        let pipeline = Arc::new(
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
+        );
+        MotionBlurCommandFactory {
+            pipeline,
+            infos: CpuBufferPool::new(device, BufferUsage::uniform_buffer()),
+        }
+    }
+}
+
+impl CommandFactory for MotionBlurCommandFactory {
+    fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>) {
+        let CommandFactoryContext { app_info, buffers, shutter, .. } = ctx;
+        if shutter.samples <= 1 {
+            return;
+        }
+
+        let layout_1 = self.pipeline.layout().descriptor_set_layout(1).unwrap();
+        let info = cs::ty::ShutterInfo {
+            shutter_sample: shutter.sample,
+            shutter_samples: shutter.samples,
+        };
+        let set_1 = Arc::new(
+            PersistentDescriptorSet::start(layout_1.clone())
+                .add_buffer(self.infos.next(info).unwrap())
+                .unwrap()
+                .add_buffer(buffers.accumulation.clone())
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        for tile in app_info.tiles() {
+            let mut command = AutoCommandBufferBuilder::new(
+                app_info.device.clone(),
+                app_info.graphics_queue.family(),
+            )
+            .unwrap();
+
+            command
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    (buffers.global_app_set.clone(), set_1.clone()),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
+                .unwrap();
+
+            commands.push(command.build().unwrap());
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "motion_blur"
+    }
+}
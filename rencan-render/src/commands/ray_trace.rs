@@ -9,7 +9,7 @@ use vulkano::{
 
 use crate::core::{CommandFactory, CommandFactoryContext, Screen};
 use std::cell::RefCell;
-use crate::core::camera::Camera;
+use crate::core::camera::{Camera, Shutter};
 use nalgebra::Point3;
 
 mod cs {
@@ -27,20 +27,16 @@ pub struct RayTraceCommandFactory {
     pipeline: Arc<ComputePipeline<PipelineLayout<cs::Layout>>>,
     prev_camera: RefCell<Camera>,
     prev_screen: RefCell<Screen>,
-    local_size_x: u32,
+    prev_shutter: RefCell<Option<Shutter>>,
+    prev_scene_version: RefCell<Option<u64>>,
 }
 
 impl RayTraceCommandFactory {
     pub fn new(device: Arc<Device>) -> Self {
         let shader = cs::Shader::load(device.clone()).unwrap();
-        let local_size_x = device.physical_device().extended_properties().subgroup_size().unwrap_or(32);
-
-        let constants = cs::SpecializationConstants {
-            constant_0: local_size_x,
-        };
 
         let pipeline = Arc::new(
-            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &constants, None).unwrap(),
+            ComputePipeline::new(device.clone(), &shader.main_entry_point(), &(), None).unwrap(),
         );
         RayTraceCommandFactory {
             pipeline,
@@ -50,7 +46,8 @@ impl RayTraceCommandFactory {
                 0.0,
             )),
             prev_screen: RefCell::new(Screen::new(0, 0)),
-            local_size_x,
+            prev_shutter: RefCell::new(None),
+            prev_scene_version: RefCell::new(None),
         }
     }
 }
@@ -58,14 +55,19 @@ impl RayTraceCommandFactory {
 impl CommandFactory for RayTraceCommandFactory {
     fn make_command(&self, ctx: CommandFactoryContext, commands: &mut Vec<AutoCommandBuffer>,
     )  {
+        let scene_version = Some(ctx.scene.version());
         if *self.prev_screen.borrow() == ctx.app_info.screen
             && *self.prev_camera.borrow() == *ctx.camera
+            && *self.prev_shutter.borrow() == Some(ctx.shutter)
+            && *self.prev_scene_version.borrow() == scene_version
         {
             return;
         }
 
         *self.prev_camera.borrow_mut() = ctx.camera.clone();
         *self.prev_screen.borrow_mut() = ctx.app_info.screen.clone();
+        *self.prev_shutter.borrow_mut() = Some(ctx.shutter);
+        *self.prev_scene_version.borrow_mut() = scene_version;
 
         let CommandFactoryContext { app_info, buffers, .. } = ctx;
         let device = app_info.device.clone();
@@ -74,16 +76,27 @@ impl CommandFactory for RayTraceCommandFactory {
 
         let set_1 = buffers.models_set.clone();
 
-        let mut command =
-            AutoCommandBufferBuilder::new(device.clone(), app_info.graphics_queue.family())
+        for tile in app_info.tiles() {
+            let mut command =
+                AutoCommandBufferBuilder::new(device.clone(), app_info.graphics_queue.family())
+                    .unwrap();
+
+            command
+                .dispatch(
+                    tile.workgroups(),
+                    self.pipeline.clone(),
+                    (set_0.clone(), set_1.clone()),
+                    cs::ty::Tile { tile_offset: tile.offset },
+                )
                 .unwrap();
 
-        command
-            .dispatch([ctx.app_info.size_of_image_array() as u32 / self.local_size_x, 1, 1], self.pipeline.clone(), (set_0, set_1), ())
-            .unwrap();
+            let command = command.build().unwrap();
 
-        let command = command.build().unwrap();
+            commands.push(command);
+        }
+    }
 
-        commands.push(command);
+    fn name(&self) -> &'static str {
+        "ray_trace"
     }
 }
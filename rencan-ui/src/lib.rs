@@ -15,11 +15,15 @@ use vulkano::{
 use vulkano_win::VkSurfaceBuild;
 use winit::{
     dpi::{PhysicalSize, Size},
+    event::VirtualKeyCode,
     event_loop::EventLoop,
     window::{Window, WindowBuilder},
 };
 
-use rencan_core::{camera::Camera, AppInfo, Scene, Screen};
+use rencan_core::{
+    camera::Camera, debug_view::DebugView, frame_stats::FrameStats, intersection::PickResult,
+    AppInfo, Scene, Screen,
+};
 use rencan_render::{App, AppBuilder};
 use vulkano::image::AttachmentImage;
 use vulkano::swapchain::SupportedPresentModes;
@@ -162,9 +166,50 @@ impl GuiApp {
         self.prev = Some(Box::new(fut));
     }
 
+    /// Returns the hit under the pixel. Waits for the previous frame to finish.
+    pub fn pick(&mut self, x: u32, y: u32) -> Option<PickResult> {
+        self.wait_previous_frame();
+        self.app.pick(x, y)
+    }
+
+    /// Returns all hits inside the rectangle. Waits for the previous frame to finish.
+    pub fn pick_rect(&mut self, x: u32, y: u32, width: u32, height: u32) -> Vec<PickResult> {
+        self.wait_previous_frame();
+        self.app.pick_rect(x, y, width, height)
+    }
+
+    fn wait_previous_frame(&mut self) {
+        if let Some(prev) = self.prev.take() {
+            prev.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+        }
+        self.prev = Some(Box::new(vulkano::sync::now(self.device())));
+    }
+
     pub fn app_mut(&mut self) -> &mut App {
         &mut self.app
     }
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.app.frame_stats()
+    }
+    /// Handles key bindings of the gui: `Tab` cycles debug views, `1`-`9` select one of them.
+    /// Returns `true` if the key was consumed.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> bool {
+        let view = match key {
+            VirtualKeyCode::Tab => self.app.debug_view().next(),
+            VirtualKeyCode::Key1 => DebugView::ALL[0],
+            VirtualKeyCode::Key2 => DebugView::ALL[1],
+            VirtualKeyCode::Key3 => DebugView::ALL[2],
+            VirtualKeyCode::Key4 => DebugView::ALL[3],
+            VirtualKeyCode::Key5 => DebugView::ALL[4],
+            VirtualKeyCode::Key6 => DebugView::ALL[5],
+            VirtualKeyCode::Key7 => DebugView::ALL[6],
+            VirtualKeyCode::Key8 => DebugView::ALL[7],
+            VirtualKeyCode::Key9 => DebugView::ALL[8],
+            _ => return false,
+        };
+        self.app.set_debug_view(view);
+        true
+    }
     pub fn device(&self) -> Arc<Device> {
         self.app.info().device.clone()
     }
@@ -221,7 +266,14 @@ fn init_app(window: &Arc<Surface<Window>>,instance: Arc<Instance>, screen: Scree
         Camera::from_origin().move_at(0.0, 0.0, 5.0),
     )
     .then_ray_tracing_pipeline()
+    .then_command(Box::new(rencan_render::commands::AmbientOcclusionCommandFactory::new(
+        device.clone(),
+    )))
     .then_command(Box::new(rencan_render::commands::LightningCommandFactory::new(device.clone())))
+    .then_denoise(rencan_render::commands::DenoiseSettings::default())
+    .then_command(Box::new(rencan_render::commands::MotionBlurCommandFactory::new(
+        device.clone(),
+    )))
     .build();
 
     (app, present_queue)